@@ -0,0 +1,5 @@
+pub mod background;
+pub mod color_background;
+pub mod gradient_background;
+pub mod image_background;
+pub mod sky_background;
@@ -0,0 +1,9 @@
+use crate::color::Color;
+use glam::DVec3;
+use std::fmt::Debug;
+
+// Trait for what is seen when a ray does not hit any object
+pub trait Background: Debug {
+    // Gets the light coming from infinitely far away in a direction
+    fn get_color(&self, dir: DVec3) -> Color;
+}
@@ -0,0 +1,21 @@
+use crate::backgrounds::background::Background;
+use crate::color::Color;
+use glam::DVec3;
+
+// A background of a single color in every direction
+#[derive(Debug)]
+pub struct ColorBackground {
+    color: Color,
+}
+
+impl ColorBackground {
+    pub fn new(color: Color) -> Self {
+        ColorBackground { color }
+    }
+}
+
+impl Background for ColorBackground {
+    fn get_color(&self, _: DVec3) -> Color {
+        self.color
+    }
+}
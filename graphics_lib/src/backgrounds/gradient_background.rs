@@ -0,0 +1,28 @@
+use crate::backgrounds::background::Background;
+use crate::color::Color;
+use glam::DVec3;
+
+// A background that blends between two colors, from straight down to straight up
+#[derive(Debug)]
+pub struct GradientBackground {
+    bottom: Color,
+    top: Color,
+    up: DVec3,
+}
+
+impl GradientBackground {
+    pub fn new(bottom: Color, top: Color, up: DVec3) -> Self {
+        GradientBackground {
+            bottom,
+            top,
+            up: up.normalize(),
+        }
+    }
+}
+
+impl Background for GradientBackground {
+    fn get_color(&self, dir: DVec3) -> Color {
+        let t = (dir.normalize().dot(self.up) + 1.) / 2.;
+        self.bottom * (1. - t) + self.top * t
+    }
+}
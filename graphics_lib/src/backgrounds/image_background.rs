@@ -0,0 +1,52 @@
+use crate::backgrounds::background::Background;
+use crate::color::Color;
+use crate::image::Image;
+use glam::DVec3;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+
+// A background from an equirectangular (latitude/longitude) image, with +y up
+// The center of the image faces +z
+#[derive(Debug)]
+pub struct ImageBackground {
+    image: Image,
+    intensity: f64,
+    // Rotation around the y axis in radians
+    rotation: f64,
+}
+
+impl ImageBackground {
+    pub fn new(image: Image, intensity: f64, rotation: f64) -> Self {
+        ImageBackground {
+            image,
+            intensity,
+            rotation,
+        }
+    }
+
+    // Loads the background from a Radiance HDR file
+    // Note this can fail!
+    pub fn from_hdr_file(
+        file: BufReader<File>,
+        intensity: f64,
+        rotation: f64,
+    ) -> Result<Self, String> {
+        Ok(ImageBackground::new(
+            Image::from_hdr_file(file)?,
+            intensity,
+            rotation,
+        ))
+    }
+}
+
+impl Background for ImageBackground {
+    fn get_color(&self, dir: DVec3) -> Color {
+        let dir = dir.normalize();
+
+        let u = 0.5 + (dir.x.atan2(dir.z) - self.rotation) / (2. * PI);
+        let v = dir.y.clamp(-1., 1.).acos() / PI;
+
+        self.image.sample(u, v) * self.intensity
+    }
+}
@@ -0,0 +1,214 @@
+use crate::backgrounds::background::Background;
+use crate::color::Color;
+use crate::constants::CHANNEL_WAVELENGTHS;
+use crate::lights::sun_light::SunLight;
+use glam::DVec3;
+use std::f64::consts::PI;
+
+// Number of directions averaged to find the light reflected by the ground
+const GROUND_SAMPLES: usize = 32;
+
+// Analytic daylight sky using the Preetham model
+// Based on "A Practical Analytic Model for Daylight", Preetham, Shirley and Smits 1999
+// The sky is brightest around the sun, with a blue zenith and a whiter horizon
+//  that gets hazier as the turbidity goes up (2 is very clear, 10 is hazy)
+// Below the horizon is ground, reflecting the sky by the ground albedo
+#[derive(Debug)]
+pub struct SkyBackground {
+    // Direction from the ground towards the sun, +y is up
+    sun_direction: DVec3,
+    turbidity: f64,
+    // Scales the luminance of the model, which is in thousands of candela per
+    //  square metre, values around 0.05 match the brightness of the other lights
+    intensity: f64,
+    // Perez distribution coefficients for luminance and the two chromaticities
+    perez_luminance: [f64; 5],
+    perez_x: [f64; 5],
+    perez_y: [f64; 5],
+    // Sky values straight up
+    zenith: DVec3,
+    ground: Color,
+}
+
+impl SkyBackground {
+    pub fn new(sun_direction: DVec3, turbidity: f64, ground_albedo: Color, intensity: f64) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let t = turbidity;
+
+        // The model is only valid for a sun above the horizon
+        let theta_s = sun_direction.y.clamp(0.01, 1.).acos();
+
+        let perez_luminance = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let perez_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let perez_y = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        // Zenith luminance and chromaticity
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let thetas = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.];
+        let turbidities = [t * t, t, 1.];
+        let zenith_x = polynomial(
+            &turbidities,
+            &[
+                [0.00166, -0.00375, 0.00209, 0.],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ],
+            &thetas,
+        );
+        let zenith_y = polynomial(
+            &turbidities,
+            &[
+                [0.00275, -0.00610, 0.00317, 0.],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ],
+            &thetas,
+        );
+
+        let mut sky = SkyBackground {
+            sun_direction,
+            turbidity,
+            intensity,
+            perez_luminance,
+            perez_x,
+            perez_y,
+            zenith: DVec3::new(zenith_luminance.max(0.), zenith_x, zenith_y),
+            ground: Color::new_black(),
+        };
+
+        // The ground reflects the average of the sky above it
+        let average_sky = (0..GROUND_SAMPLES)
+            .flat_map(|i| {
+                (0..GROUND_SAMPLES).map(move |j| {
+                    let theta = (i as f64 + 0.5) / GROUND_SAMPLES as f64 * PI / 2.;
+                    let phi = (j as f64 + 0.5) / GROUND_SAMPLES as f64 * 2. * PI;
+                    (theta, phi)
+                })
+            })
+            .map(|(theta, phi)| {
+                let dir = DVec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                // Weighted by how much of the sky each direction covers and how
+                //  directly it shines on the ground
+                sky.sky_color(dir) * (theta.sin() * theta.cos())
+            })
+            .sum::<Color>()
+            * (1. / (GROUND_SAMPLES * GROUND_SAMPLES) as f64 * PI);
+
+        sky.ground = average_sky.piecewise_mul(&ground_albedo);
+        sky
+    }
+
+    // Color of the sun after passing through the atmosphere, for a sun of intensity 1
+    // Uses Rayleigh scattering for the air and Angstrom's formula for the haze
+    pub fn sun_color(&self) -> Color {
+        let theta_s = self.sun_direction.y.clamp(0., 1.).acos();
+
+        // Relative thickness of the atmosphere the sunlight passes through
+        let air_mass =
+            1. / (theta_s.cos() + 0.50572 * (96.07995 - theta_s.to_degrees()).powf(-1.6364));
+
+        let beta = 0.04608 * self.turbidity - 0.04586;
+
+        let transmittance = |wavelength: f64| {
+            let rayleigh = 0.008735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            (-(rayleigh + aerosol) * air_mass).exp()
+        };
+
+        let [red, green, blue] = CHANNEL_WAVELENGTHS.map(transmittance);
+        Color::new(red, green, blue)
+    }
+
+    // Makes a sun light matching the sky, shining from the sun direction
+    // The center and radius should contain the whole scene
+    pub fn sun_light(&self, intensity: f64, center: DVec3, radius: f64) -> SunLight {
+        SunLight::new(
+            -self.sun_direction,
+            self.sun_color() * intensity,
+            center,
+            radius,
+        )
+    }
+
+    // Sky color for a direction above the horizon
+    fn sky_color(&self, dir: DVec3) -> Color {
+        let cos_theta = dir.y.max(1.0E-3);
+        let gamma = dir.dot(self.sun_direction).clamp(-1., 1.).acos();
+        let theta_s = self.sun_direction.y.clamp(0.01, 1.).acos();
+
+        // Each value is its zenith value scaled by the Perez distribution
+        let value = |zenith: f64, coefficients: &[f64; 5]| {
+            zenith * perez(coefficients, cos_theta, gamma) / perez(coefficients, 1., theta_s)
+        };
+
+        let luminance = value(self.zenith.x, &self.perez_luminance) * self.intensity;
+        let x = value(self.zenith.y, &self.perez_x);
+        let y = value(self.zenith.z, &self.perez_y).max(1.0E-6);
+
+        // xyY to XYZ, then XYZ to linear RGB
+        let big_x = x / y * luminance;
+        let big_z = (1. - x - y) / y * luminance;
+
+        Color::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.),
+        )
+    }
+}
+
+impl Background for SkyBackground {
+    fn get_color(&self, dir: DVec3) -> Color {
+        let dir = dir.normalize();
+        if dir.y < 0. {
+            self.ground
+        } else {
+            self.sky_color(dir)
+        }
+    }
+}
+
+// The Perez sky distribution function
+fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+// Evaluates the zenith chromaticity polynomials of turbidity and sun angle
+fn polynomial(turbidities: &[f64; 3], matrix: &[[f64; 4]; 3], thetas: &[f64; 4]) -> f64 {
+    turbidities
+        .iter()
+        .zip(matrix.iter())
+        .map(|(t, row)| {
+            t * row
+                .iter()
+                .zip(thetas.iter())
+                .map(|(m, th)| m * th)
+                .sum::<f64>()
+        })
+        .sum()
+}
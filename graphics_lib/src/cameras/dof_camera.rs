@@ -49,10 +49,9 @@ impl Camera for DoFCamera {
         let mut rng = rand::thread_rng();
 
         // Direction if this was a camera without DOF effect
-        let normal_dir = ((self.direction * self.zoom)
-            + (self.up * (y as f64 / 2.))
-            + (self.right * (x as f64 / 2.)))
-            .normalize();
+        let normal_dir =
+            ((self.direction * self.zoom) + (self.up * (y / 2.)) + (self.right * (x / 2.)))
+                .normalize();
 
         // Work out focal point from normal camera ray
         let focal_point = self.position + normal_dir * self.focal_length;
@@ -33,9 +33,7 @@ impl Camera for NormalCamera {
     fn rays(&self, x: f64, y: f64) -> Vec<Ray> {
         vec![Ray::new(
             self.position,
-            (self.direction * self.zoom)
-                + (self.up * (y as f64 / 2.))
-                + (self.right * (x as f64 / 2.)),
+            (self.direction * self.zoom) + (self.up * (y / 2.)) + (self.right * (x / 2.)),
         )]
     }
 }
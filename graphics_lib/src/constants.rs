@@ -2,10 +2,33 @@
 pub(crate) const EPSILON: f64 = 1.0E-6;
 // Minimum coefficient for a color calculation before its ignored
 pub(crate) const MIN_RECURSE_COEFFICIENT: f64 = 1.0E-8;
+// Distance from the ends of a shadow ray that hits are ignored within
+pub(crate) const SHADOW_EPSILON: f64 = 0.01;
 // Maximum number of recursions for a color calculation before its ignored
 pub(crate) const MAX_RECURSE_DEPTH: usize = 10;
 // Maximum number of recursions for a photon calculation before its ignored
 pub(crate) const MAX_PHOTON_RECURSE_DEPTH: usize = 6;
+// Most transparent objects a ray can be inside of at once, for nested glass and liquids
+pub(crate) const MAX_NESTED_MEDIA: usize = 8;
+// Default number of rays used for blurry reflections of rough surfaces
+pub(crate) const DEFAULT_GLOSSY_SAMPLES: usize = 8;
+// Default number of random walks under the surface of subsurface scattering materials
+pub(crate) const DEFAULT_SUBSURFACE_SAMPLES: usize = 16;
+// Most times light can scatter under a surface before it is treated as absorbed
+pub(crate) const MAX_SUBSURFACE_EVENTS: usize = 256;
+// Fewest steps rays take through fog and volumes to find the light scattered
+pub(crate) const VOLUME_STEPS: usize = 16;
+// Most steps rays take through volumes with detail, such as voxel grids
+pub(crate) const MAX_VOLUME_STEPS: usize = 64;
+// Furthest depth given for a pixel, hits further away are given this depth
+pub(crate) const MAX_DEPTH: f64 = 100.;
+// Furthest distance fog is followed along rays that do not hit anything
+pub(crate) const FOG_DISTANCE: f64 = 100.;
+// Wavelengths in micrometres used for the red, green and blue color channels
+pub(crate) const CHANNEL_WAVELENGTHS: [f64; 3] = [0.65, 0.55, 0.45];
+// Closest distance used for the falloff of lights, stops the intensity going to
+//  infinity at the light
+pub(crate) const MIN_FALLOFF_DISTANCE: f64 = 0.01;
 // Default ambient coefficient for objects
 pub(crate) const DEFAULT_AMBIENT: f64 = 0.015;
 // Scene bounds, this is needed for the bounds of primitives as they cannot
@@ -14,8 +37,13 @@ pub(crate) const SCENE_BOUNDS: f32 = 1.0E10;
 // How many photons and caustic photons to fire
 pub(crate) const NUMBER_PHOTONS_PER_LIGHT: usize = 500_000;
 pub(crate) const NUMBER_CAUSTICS_PER_LIGHT_PER_OBJ: usize = 100_000;
+// Number of directions an environment light is split into for importance sampling
+pub(crate) const ENVIRONMENT_DIRECTIONS: usize = 4096;
 // Radius to find photons in when estimating radiance
 pub(crate) const PHOTON_RAD: f64 = 0.8;
 // Radius to find caustics in when estimating caustic effect
 pub(crate) const CAUSTIC_RAD: f64 = 0.05;
 pub(crate) const CAUSTIC_SCALAR: f64 = 0.5;
+// Roughest reflective surface that makes caustics, rougher ones spread the light too
+//  much to focus it
+pub(crate) const MAX_CAUSTIC_ROUGHNESS: f64 = 0.5;
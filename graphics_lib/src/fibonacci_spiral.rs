@@ -8,11 +8,14 @@ const NUMBER_POINTS: usize = 100_000_000;
 // Generates a vector in a "random" direction
 pub(crate) fn fibonacci_spiral_random() -> DVec3 {
     let mut rng = rand::thread_rng();
-    let i = rng.gen_range(0..NUMBER_POINTS);
+    fibonacci_spiral_point(rng.gen_range(0..NUMBER_POINTS), NUMBER_POINTS)
+}
 
+// Gets the i-th of n points spread evenly over the sphere
+pub(crate) fn fibonacci_spiral_point(i: usize, n: usize) -> DVec3 {
     let phi = PI * (3. - 5.0_f64.sqrt());
 
-    let y = 1. - (i as f64 / (NUMBER_POINTS - 1) as f64) * 2.; // y goes from 1 to -1
+    let y = 1. - (i as f64 / (n - 1) as f64) * 2.; // y goes from 1 to -1
     let radius = (1. - y * y).sqrt(); // radius at y
 
     let theta = phi * (i as f64); // golden angle increment
@@ -33,3 +36,15 @@ pub(crate) fn hemisphere_random(dir: DVec3) -> DVec3 {
         rand_dir
     }
 }
+
+// Generates a vector in a hemisphere around a vector, more likely to be close to
+//  that vector, following the cosine distribution of a Lambertian surface
+pub(crate) fn cosine_hemisphere_random(dir: DVec3) -> DVec3 {
+    let mut rng = rand::thread_rng();
+    loop {
+        let rand_dir = hemisphere_random(dir);
+        if rng.gen_range(0. ..1.) < dir.dot(rand_dir) {
+            return rand_dir;
+        }
+    }
+}
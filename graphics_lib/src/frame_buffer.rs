@@ -48,8 +48,8 @@ impl FrameBuffer {
 
     // Adds color for a pixel
     pub fn plot_pixel(&mut self, x: usize, y: usize, red: f64, green: f64, blue: f64) {
-        let x = x.max(0).min(self.width - 1);
-        let y = y.max(0).min(self.height - 1);
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
         self.frame_buffer[y * self.width + x].red = red;
         self.frame_buffer[y * self.width + x].green = green;
         self.frame_buffer[y * self.width + x].blue = blue;
@@ -57,8 +57,8 @@ impl FrameBuffer {
 
     // Adds depth imformation for a pixel
     pub fn plot_depth(&mut self, x: usize, y: usize, depth: f64) {
-        let x = x.max(0).min(self.width - 1);
-        let y = y.max(0).min(self.height - 1);
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
         self.frame_buffer[y * self.width + x].depth = depth;
     }
 
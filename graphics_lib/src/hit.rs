@@ -1,4 +1,4 @@
-use glam::DVec3;
+use glam::{DVec2, DVec3};
 
 // Represents a hit between a ray and a primitive
 #[derive(Clone)]
@@ -15,6 +15,14 @@ pub struct Hit {
     correct_dir: bool,
     // CSG of the leaf the object hit in the CSG tree
     csg_index: usize,
+    // Texture coordinates of the hit, zero for primitives without them
+    uv: DVec2,
+    // Barycentric coordinates of the hit within a triangle, zero for other primitives
+    barycentric: DVec3,
+    // Directions along the surface that u and v increase in, perpendicular to the normal
+    // Primitives without texture coordinates use any pair perpendicular to the normal
+    tangent: DVec3,
+    bitangent: DVec3,
 }
 
 impl Hit {
@@ -26,13 +34,66 @@ impl Hit {
         obj_index: usize,
         csg_index: usize,
     ) -> Hit {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = normal.any_orthonormal_pair();
         Hit {
             pos,
-            normal: normal.normalize(),
+            normal,
             distance,
             correct_dir,
             obj_index,
             csg_index,
+            uv: DVec2::ZERO,
+            barycentric: DVec3::ZERO,
+            tangent,
+            bitangent,
+        }
+    }
+
+    // Adds texture coordinates to a hit
+    pub(crate) fn with_uv(mut self, uv: DVec2) -> Hit {
+        self.uv = uv;
+        self
+    }
+
+    // Adds barycentric coordinates to a hit
+    pub(crate) fn with_barycentric(mut self, barycentric: DVec3) -> Hit {
+        self.barycentric = barycentric;
+        self
+    }
+
+    // Adds the directions u and v increase in along the surface
+    // Directions that are parallel to the normal are ignored
+    pub(crate) fn with_tangents(mut self, tangent: DVec3, bitangent: DVec3) -> Hit {
+        let flatten = |v: DVec3| (v - self.normal * self.normal.dot(v)).try_normalize();
+        if let (Some(tangent), Some(bitangent)) = (flatten(tangent), flatten(bitangent)) {
+            self.tangent = tangent;
+            self.bitangent = bitangent;
+        }
+        self
+    }
+
+    // Replaces the shading normal, keeping the tangents perpendicular to it
+    // Used by materials that perturb the normal
+    pub(crate) fn with_normal(self, normal: DVec3) -> Hit {
+        let (tangent, bitangent) = (self.tangent, self.bitangent);
+        let normal = normal.normalize();
+        let (default_tangent, default_bitangent) = normal.any_orthonormal_pair();
+        Hit {
+            normal,
+            tangent: default_tangent,
+            bitangent: default_bitangent,
+            ..self
+        }
+        .with_tangents(tangent, bitangent)
+    }
+
+    // A copy of the hit moved along the surface, used to find how textures change
+    pub(crate) fn moved(&self, pos: DVec3, uv: DVec2) -> Hit {
+        Hit {
+            pos: self.pos + pos,
+            uv: self.uv + uv,
+            ..self.clone()
         }
     }
 
@@ -60,6 +121,22 @@ impl Hit {
         self.csg_index
     }
 
+    pub(crate) fn uv(&self) -> DVec2 {
+        self.uv
+    }
+
+    pub fn barycentric(&self) -> DVec3 {
+        self.barycentric
+    }
+
+    pub(crate) fn tangent(&self) -> DVec3 {
+        self.tangent
+    }
+
+    pub(crate) fn bitangent(&self) -> DVec3 {
+        self.bitangent
+    }
+
     // Reverses the direction of a hit
     // Used by CSG's to modify hits entering a object to instead be exiting
     pub(crate) fn flip(&mut self) {
@@ -0,0 +1,412 @@
+use crate::color::Color;
+use std::io::BufRead;
+
+// An image stored as colors, used for environment maps and textures
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert!(width > 0 && height > 0);
+        assert_eq!(width * height, pixels.len());
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Reads a Radiance HDR (.hdr) file, in either flat or run length encoded RGBE
+    // Note this can fail!
+    pub fn from_hdr_file(file: impl BufRead) -> Result<Image, String> {
+        let mut file = file;
+        let mut line = String::new();
+
+        file.read_line(&mut line)
+            .map_err(|_| "Failed to read line.")?;
+        if !line.starts_with("#?") {
+            return Err("Wrong file format.".to_string());
+        }
+
+        // Header lines continue until an empty line
+        loop {
+            line.clear();
+            if file
+                .read_line(&mut line)
+                .map_err(|_| "Failed to read line.")?
+                == 0
+            {
+                return Err("Header not terminated.".to_string());
+            }
+
+            let header = line.trim();
+            if header.is_empty() {
+                break;
+            }
+            if header.starts_with("FORMAT=") && header != "FORMAT=32-bit_rle_rgbe" {
+                return Err("Only RGBE HDR files are supported.".to_string());
+            }
+        }
+
+        // Resolution line, only the standard orientation is supported
+        line.clear();
+        file.read_line(&mut line)
+            .map_err(|_| "Failed to read line.")?;
+        let resolution = line.split_whitespace().collect::<Vec<&str>>();
+        if resolution.len() != 4 || resolution[0] != "-Y" || resolution[2] != "+X" {
+            return Err("Resolution line malformed.".to_string());
+        }
+        let height = resolution[1]
+            .parse::<usize>()
+            .map_err(|_| "Height malformed.")?;
+        let width = resolution[3]
+            .parse::<usize>()
+            .map_err(|_| "Width malformed.")?;
+        if width == 0 || height == 0 {
+            return Err("Image is empty.".to_string());
+        }
+
+        let mut data = vec![];
+        file.read_to_end(&mut data)
+            .map_err(|_| "Failed to read pixel data.")?;
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut data = data.into_iter();
+
+        for y in 0..height {
+            let scanline = read_rgbe_scanline(&mut data, width)
+                .map_err(|e| format!("Scanline {} malformed ({}).", y, e))?;
+            pixels.extend(scanline.chunks(4).map(rgbe_to_color));
+        }
+
+        Ok(Image::new(width, height, pixels))
+    }
+
+    // Reads a binary (P6) or plain text (P3) PPM file
+    // The frame buffer square roots colors when writing images, so the colors are
+    //  squared here to match
+    // Note this can fail!
+    pub fn from_ppm_file(file: impl BufRead) -> Result<Image, String> {
+        read_ppm(file, true)
+    }
+
+    // Reads a PPM file keeping the values as they are stored, for images holding
+    //  data rather than colors such as normal maps
+    // Note this can fail!
+    pub fn from_linear_ppm_file(file: impl BufRead) -> Result<Image, String> {
+        read_ppm(file, false)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+
+    // Samples the image with bilinear filtering
+    // u and v go from 0 to 1 across the image, u wraps around horizontally
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        let x = u.rem_euclid(1.) * self.width as f64 - 0.5;
+        let y = v.clamp(0., 1.) * self.height as f64 - 0.5;
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let (fx, fy) = (x - x0, y - y0);
+
+        let x0 = (x0 as i64).rem_euclid(self.width as i64) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y0.max(0.) as usize;
+        let y1 = y0 + 1;
+
+        self.get_pixel(x0, y0) * ((1. - fx) * (1. - fy))
+            + self.get_pixel(x1, y0) * (fx * (1. - fy))
+            + self.get_pixel(x0, y1) * ((1. - fx) * fy)
+            + self.get_pixel(x1, y1) * (fx * fy)
+    }
+}
+
+// Reads a PPM file, optionally squaring the values to undo the frame buffers square root
+fn read_ppm(file: impl BufRead, squared: bool) -> Result<Image, String> {
+    let mut file = file;
+    let mut data = vec![];
+    file.read_to_end(&mut data)
+        .map_err(|_| "Failed to read file.")?;
+
+    let mut position = 0;
+    let magic = read_ppm_token(&data, &mut position)?;
+    if magic != "P6" && magic != "P3" {
+        return Err("Wrong file format.".to_string());
+    }
+
+    let width = read_ppm_token(&data, &mut position)?
+        .parse::<usize>()
+        .map_err(|_| "Width malformed.")?;
+    let height = read_ppm_token(&data, &mut position)?
+        .parse::<usize>()
+        .map_err(|_| "Height malformed.")?;
+    if width == 0 || height == 0 {
+        return Err("Image is empty.".to_string());
+    }
+    let max_value = read_ppm_token(&data, &mut position)?
+        .parse::<usize>()
+        .map_err(|_| "Maximum value malformed.")?;
+    if max_value == 0 || max_value > 65535 {
+        return Err("Maximum value out of range.".to_string());
+    }
+
+    let values: Vec<usize> = if magic == "P6" {
+        // A single whitespace character separates the header from the data
+        let bytes = data.get(position + 1..).ok_or("Missing pixel data.")?;
+        if max_value < 256 {
+            bytes.iter().map(|b| *b as usize).collect()
+        } else {
+            bytes
+                .chunks_exact(2)
+                .map(|b| (b[0] as usize) << 8 | b[1] as usize)
+                .collect()
+        }
+    } else {
+        let mut values = vec![];
+        while let Ok(token) = read_ppm_token(&data, &mut position) {
+            values.push(
+                token
+                    .parse::<usize>()
+                    .map_err(|_| "Pixel value malformed.")?,
+            );
+        }
+        values
+    };
+
+    if values.len() < width * height * 3 {
+        return Err("Not enough pixel data.".to_string());
+    }
+
+    let pixels = values
+        .chunks_exact(3)
+        .take(width * height)
+        .map(|c| {
+            let scale = |v: usize| {
+                let value = v as f64 / max_value as f64;
+                if squared {
+                    value * value
+                } else {
+                    value
+                }
+            };
+            Color::new(scale(c[0]), scale(c[1]), scale(c[2]))
+        })
+        .collect();
+
+    Ok(Image::new(width, height, pixels))
+}
+
+// Reads the next whitespace separated token of a PPM file, skipping comments
+fn read_ppm_token(data: &[u8], position: &mut usize) -> Result<String, String> {
+    loop {
+        match data.get(*position) {
+            None => return Err("Unexpected end of file.".to_string()),
+            Some(b'#') => {
+                while data.get(*position).is_some_and(|c| *c != b'\n') {
+                    *position += 1;
+                }
+            }
+            Some(c) if c.is_ascii_whitespace() => *position += 1,
+            Some(_) => break,
+        }
+    }
+
+    let start = *position;
+    while data
+        .get(*position)
+        .is_some_and(|c| !c.is_ascii_whitespace())
+    {
+        *position += 1;
+    }
+
+    String::from_utf8(data[start..*position].to_vec()).map_err(|_| "Malformed header.".to_string())
+}
+
+// Reads one scanline of RGBE bytes, decoding the run length encoding if it is used
+fn read_rgbe_scanline(
+    data: &mut impl Iterator<Item = u8>,
+    width: usize,
+) -> Result<Vec<u8>, String> {
+    let mut next = || data.next().ok_or("Unexpected end of file.");
+
+    let start = [next()?, next()?, next()?, next()?];
+
+    // Run length encoded scanlines start with 2 2 followed by the width
+    if !(8..0x8000).contains(&width)
+        || start[0] != 2
+        || start[1] != 2
+        || start[2] & 0x80 != 0
+        || ((start[2] as usize) << 8 | start[3] as usize) != width
+    {
+        // Flat scanline
+        let mut scanline = start.to_vec();
+        for _ in 4..width * 4 {
+            scanline.push(next()?);
+        }
+        return Ok(scanline);
+    }
+
+    // Each of the 4 components are stored one after another
+    let mut scanline = vec![0; width * 4];
+    for component in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next()? as usize;
+            if count > 128 {
+                // A run of the same value
+                let count = count - 128;
+                if x + count > width {
+                    return Err("Run too long.".to_string());
+                }
+                let value = next()?;
+                for _ in 0..count {
+                    scanline[x * 4 + component] = value;
+                    x += 1;
+                }
+            } else {
+                // A run of different values
+                if count == 0 || x + count > width {
+                    return Err("Bad run length.".to_string());
+                }
+                for _ in 0..count {
+                    scanline[x * 4 + component] = next()?;
+                    x += 1;
+                }
+            }
+        }
+    }
+
+    Ok(scanline)
+}
+
+// RGBE stores a shared exponent for the three color components
+fn rgbe_to_color(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        Color::new_black()
+    } else {
+        let scale = 2_f64.powi(rgbe[3] as i32 - (128 + 8));
+        Color::new(
+            rgbe[0] as f64 * scale,
+            rgbe[1] as f64 * scale,
+            rgbe[2] as f64 * scale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn assert_color(color: Color, red: f64, green: f64, blue: f64) {
+        assert!((color.red() - red).abs() < 1E-9, "{:?}", color);
+        assert!((color.green() - green).abs() < 1E-9, "{:?}", color);
+        assert!((color.blue() - blue).abs() < 1E-9, "{:?}", color);
+    }
+
+    const HDR_HEADER: &[u8] = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+
+    #[test]
+    fn reads_plain_ppm() {
+        let image = Image::from_linear_ppm_file(Cursor::new(
+            b"P3\n# a comment\n2 1\n255\n255 0 0 0 51 255\n",
+        ))
+        .unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_color(image.get_pixel(0, 0), 1., 0., 0.);
+        assert_color(image.get_pixel(1, 0), 0., 0.2, 1.);
+    }
+
+    #[test]
+    fn reads_binary_ppm_squared() {
+        let mut data = b"P6 1 2 255\n".to_vec();
+        data.extend([255, 0, 51, 0, 255, 0]);
+        let image = Image::from_ppm_file(Cursor::new(&data)).unwrap();
+
+        assert_eq!((image.width(), image.height()), (1, 2));
+        assert_color(image.get_pixel(0, 0), 1., 0., 0.04);
+        assert_color(image.get_pixel(0, 1), 0., 1., 0.);
+    }
+
+    #[test]
+    fn rejects_bad_ppm() {
+        let error = |data: &[u8]| Image::from_ppm_file(Cursor::new(data)).unwrap_err();
+
+        assert_eq!(error(b"P5 1 1 255\n0"), "Wrong file format.");
+        assert_eq!(error(b"P3 0 0 255\n"), "Image is empty.");
+        assert_eq!(error(b"P3 2 1 255\n0 0 0"), "Not enough pixel data.");
+        assert_eq!(error(b"P3 1 1 0\n0 0 0"), "Maximum value out of range.");
+    }
+
+    #[test]
+    fn reads_flat_hdr() {
+        let mut data = HDR_HEADER.to_vec();
+        data.extend(b"-Y 1 +X 2\n");
+        data.extend([128, 64, 32, 129, 0, 0, 0, 0]);
+        let image = Image::from_hdr_file(Cursor::new(&data)).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_color(image.get_pixel(0, 0), 1., 0.5, 0.25);
+        assert_color(image.get_pixel(1, 0), 0., 0., 0.);
+    }
+
+    #[test]
+    fn reads_run_length_encoded_hdr() {
+        let mut data = HDR_HEADER.to_vec();
+        data.extend(b"-Y 1 +X 8\n");
+        data.extend([2, 2, 0, 8]);
+        // Each component is one run of 8 of the same value
+        for value in [128, 64, 32, 129] {
+            data.extend([128 + 8, value]);
+        }
+        let image = Image::from_hdr_file(Cursor::new(&data)).unwrap();
+
+        assert_eq!((image.width(), image.height()), (8, 1));
+        for x in 0..8 {
+            assert_color(image.get_pixel(x, 0), 1., 0.5, 0.25);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_hdr() {
+        let error = |data: &[u8]| Image::from_hdr_file(Cursor::new(data)).unwrap_err();
+
+        assert_eq!(error(b"P6\n"), "Wrong file format.");
+        let mut data = HDR_HEADER.to_vec();
+        data.extend(b"-Y 0 +X 0\n");
+        assert_eq!(error(&data), "Image is empty.");
+        let mut data = HDR_HEADER.to_vec();
+        data.extend(b"+X 1 -Y 1\n");
+        assert_eq!(error(&data), "Resolution line malformed.");
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_empty_image() {
+        Image::new(0, 0, vec![]);
+    }
+
+    #[test]
+    fn sample_wraps_horizontally() {
+        let image = Image::new(2, 1, vec![Color::new_grey(0.), Color::new_grey(1.)]);
+
+        // Halfway between the last and first pixels
+        assert_color(image.sample(0., 0.5), 0.5, 0.5, 0.5);
+        assert_color(image.sample(0.75, 0.5), 1., 1., 1.);
+    }
+}
@@ -1,13 +1,20 @@
+pub mod backgrounds;
 pub mod cameras;
 pub mod color;
 mod constants;
 mod fibonacci_spiral;
 pub mod frame_buffer;
 mod hit;
+pub mod image;
 pub mod lights;
 pub mod materials;
+mod medium;
+mod microfacet;
 pub mod objects;
 mod photon;
 mod primitives;
 pub mod ray;
+pub mod render_mode;
 pub mod scene;
+pub mod textures;
+pub mod volumes;
@@ -1,2 +1,6 @@
+pub mod environment_light;
+pub mod falloff;
 pub mod light;
+pub mod object_light;
 pub mod point_light;
+pub mod sun_light;
@@ -0,0 +1,189 @@
+use crate::backgrounds::background::Background;
+use crate::color::Color;
+use crate::constants::{ENVIRONMENT_DIRECTIONS, SCENE_BOUNDS};
+use crate::fibonacci_spiral::{fibonacci_spiral_point, fibonacci_spiral_random};
+use crate::lights::light::Light;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Lights the scene from a background infinitely far away
+// The sky is split into directions that are picked in proportion to their brightness,
+//  so small bright areas like the sun in an HDR image are not missed
+// The center and radius should contain the whole scene, photons are fired from
+//  outside this sphere
+#[derive(Debug)]
+pub struct EnvironmentLight {
+    background: Arc<dyn Background + Sync + Send>,
+    num_samples: usize,
+    center: DVec3,
+    radius: f64,
+    directions: Vec<DVec3>,
+    // Running total of the brightness of each direction, used to pick directions
+    cumulative_weights: Vec<f64>,
+    // Average color of the background
+    color: Color,
+    // Average direction light arrives from, weighted by brightness
+    main_direction: DVec3,
+}
+
+impl EnvironmentLight {
+    pub fn new(
+        background: Arc<dyn Background + Sync + Send>,
+        num_samples: usize,
+        center: DVec3,
+        radius: f64,
+    ) -> Self {
+        let directions = (0..ENVIRONMENT_DIRECTIONS)
+            .map(|i| fibonacci_spiral_point(i, ENVIRONMENT_DIRECTIONS))
+            .collect::<Vec<DVec3>>();
+
+        let colors = directions
+            .iter()
+            .map(|d| background.get_color(*d))
+            .collect::<Vec<Color>>();
+
+        // Each direction has a small minimum weight so dark areas still get sampled
+        let cumulative_weights = colors
+            .iter()
+            .scan(0., |total, c| {
+                *total += c.magnitude().max(0.) + 1.0E-3;
+                Some(*total)
+            })
+            .collect::<Vec<f64>>();
+
+        let main_direction = directions
+            .iter()
+            .zip(colors.iter())
+            .fold(DVec3::ZERO, |acc, (d, c)| acc - *d * c.magnitude())
+            .try_normalize()
+            .unwrap_or(-DVec3::Y);
+
+        EnvironmentLight {
+            color: colors.into_iter().sum::<Color>() * (1. / ENVIRONMENT_DIRECTIONS as f64),
+            background,
+            num_samples: num_samples.max(1),
+            center,
+            radius,
+            directions,
+            cumulative_weights,
+            main_direction,
+        }
+    }
+
+    // Picks a direction towards the background, returning the direction and the
+    //  probability of picking that directions section of the sky
+    fn sample_direction(&self) -> (DVec3, f64) {
+        let mut rng = rand::thread_rng();
+        let total = *self.cumulative_weights.last().unwrap();
+        let i: f64 = rng.gen_range(0. ..total);
+        let index = self
+            .cumulative_weights
+            .partition_point(|w| *w < i)
+            .min(self.directions.len() - 1);
+
+        let weight = if index == 0 {
+            self.cumulative_weights[0]
+        } else {
+            self.cumulative_weights[index] - self.cumulative_weights[index - 1]
+        };
+
+        // Moves the direction randomly within the section of the sky it represents
+        let section_radius = (4. / ENVIRONMENT_DIRECTIONS as f64).sqrt();
+        let dir = (self.directions[index] + fibonacci_spiral_random() * section_radius).normalize();
+
+        (dir, weight / total)
+    }
+
+    // Color of a photon from a direction, so that on average photons are the
+    //  average color of the background
+    fn photon_color(&self, dir: DVec3, probability: f64) -> Color {
+        self.background.get_color(dir) * (1. / (ENVIRONMENT_DIRECTIONS as f64 * probability))
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn get_intensity(&self, point: DVec3, scene: &Scene, light_index: usize) -> Color {
+        self.get_samples(point, scene, light_index)
+            .into_iter()
+            .map(|(c, _)| c)
+            .sum()
+    }
+
+    fn get_direction(&self, _: DVec3) -> DVec3 {
+        self.main_direction
+    }
+
+    fn get_samples(&self, point: DVec3, scene: &Scene, _: usize) -> Vec<(Color, DVec3)> {
+        (0..self.num_samples)
+            .filter_map(|_| {
+                let (dir, probability) = self.sample_direction();
+
+                let transmittance = scene.transmittance(point, point + dir * SCENE_BOUNDS as f64);
+                if transmittance.max_val() <= 0. {
+                    return None;
+                }
+
+                // Scaled so a background of 1 in every direction gives the same light as
+                //  a point light of 1 shining straight onto the surface
+                Some((
+                    self.background.get_color(dir).piecewise_mul(&transmittance)
+                        * (4.
+                            / (ENVIRONMENT_DIRECTIONS as f64
+                                * probability
+                                * self.num_samples as f64)),
+                    -dir,
+                ))
+            })
+            .collect()
+    }
+
+    fn generate_photon_dir(&self) -> Ray {
+        self.generate_photon().0
+    }
+
+    fn generate_caustic_dir(&self, bounds: (DVec3, DVec3)) -> Ray {
+        self.generate_caustic(bounds).0
+    }
+
+    fn generate_photon(&self) -> (Ray, Color) {
+        let (dir, probability) = self.sample_direction();
+
+        // Fire from a random point on a disc facing the scene, outside the scene
+        let mut rng = rand::thread_rng();
+        let r: f64 = rng.gen_range::<f64, _>(0. ..1.).sqrt() * self.radius;
+        let theta: f64 = rng.gen_range(0. ..2. * PI);
+        let (a, b) = dir.any_orthonormal_pair();
+
+        let origin =
+            self.center + dir * self.radius + a * (r * theta.cos()) + b * (r * theta.sin());
+
+        (Ray::new(origin, -dir), self.photon_color(dir, probability))
+    }
+
+    fn generate_caustic(&self, bounds: (DVec3, DVec3)) -> (Ray, Color) {
+        if !(bounds.0.is_finite() && bounds.1.is_finite()) {
+            panic!("Non finite bounds, you may need to wrap an object in a CSG.");
+        }
+
+        let (dir, probability) = self.sample_direction();
+
+        let mut rng = rand::thread_rng();
+        let x_rand: f64 = rng.gen_range((bounds.0.x)..bounds.1.x);
+        let y_rand: f64 = rng.gen_range((bounds.0.y)..bounds.1.y);
+        let z_rand: f64 = rng.gen_range((bounds.0.z)..bounds.1.z);
+        let target = DVec3::new(x_rand, y_rand, z_rand);
+
+        (
+            Ray::new(target + dir * 2. * self.radius, -dir),
+            self.photon_color(dir, probability),
+        )
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+}
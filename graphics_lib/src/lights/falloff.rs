@@ -0,0 +1,88 @@
+use crate::constants::MIN_FALLOFF_DISTANCE;
+
+// How the intensity of a light drops off with distance
+#[derive(Debug, Clone, Copy)]
+pub enum Falloff {
+    // Light does not drop off, useful for lights far away
+    None,
+    // Physically correct falloff, 1 / d^2
+    InverseSquare,
+    // 1 / (constant + linear * d + quadratic * d^2)
+    Coefficients {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+    // Inverse square that is smoothly brought down to zero at the radius
+    // Stops lights having an effect far away from them
+    // A radius that is not above 0 gives no light at all
+    SmoothCutoff {
+        radius: f64,
+    },
+}
+
+impl Falloff {
+    // The original falloff of (1 + d / 10)^2
+    pub fn legacy() -> Falloff {
+        Falloff::Coefficients {
+            constant: 1.,
+            linear: 0.2,
+            quadratic: 0.01,
+        }
+    }
+
+    // Scale of the light intensity at a distance
+    pub(crate) fn attenuation(&self, distance: f64) -> f64 {
+        // Stops the inverse square going to infinity at the light
+        let inverse_square = 1. / distance.max(MIN_FALLOFF_DISTANCE).powi(2);
+
+        match *self {
+            Falloff::None => 1.,
+            Falloff::InverseSquare => inverse_square,
+            Falloff::Coefficients {
+                constant,
+                linear,
+                quadratic,
+            } => 1. / (constant + linear * distance + quadratic * distance * distance),
+            Falloff::SmoothCutoff { radius } if radius <= 0. => 0.,
+            Falloff::SmoothCutoff { radius } => {
+                let window = (1. - (distance / radius).powi(4)).max(0.);
+                inverse_square * window * window
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_square() {
+        assert!((Falloff::InverseSquare.attenuation(2.) - 0.25).abs() < 1E-12);
+        assert!(Falloff::InverseSquare.attenuation(0.).is_finite());
+        assert_eq!(Falloff::None.attenuation(1000.), 1.);
+    }
+
+    #[test]
+    fn legacy_matches_original_falloff() {
+        for d in [0_f64, 1., 10., 35.] {
+            let original = 1. / (1. + d / 10.).powi(2);
+            assert!((Falloff::legacy().attenuation(d) - original).abs() < 1E-12);
+        }
+    }
+
+    #[test]
+    fn smooth_cutoff_reaches_zero_at_radius() {
+        let falloff = Falloff::SmoothCutoff { radius: 5. };
+        assert!((falloff.attenuation(0.5) - 4.).abs() < 0.01);
+        assert!(falloff.attenuation(4.9) > 0.);
+        assert_eq!(falloff.attenuation(5.), 0.);
+        assert_eq!(falloff.attenuation(6.), 0.);
+    }
+
+    #[test]
+    fn zero_radius_has_no_light() {
+        assert_eq!(Falloff::SmoothCutoff { radius: 0. }.attenuation(0.), 0.);
+    }
+}
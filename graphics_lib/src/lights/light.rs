@@ -8,8 +8,28 @@ pub trait Light: Debug {
     fn get_intensity(&self, point: DVec3, scene: &Scene, light_index: usize) -> Color;
     fn get_direction(&self, point: DVec3) -> DVec3;
 
+    // Splits the light arriving at a point into a number of samples, each with
+    //  an intensity and a direction, for lights that do not come from a single point
+    // Point lights are a single sample
+    fn get_samples(&self, point: DVec3, scene: &Scene, light_index: usize) -> Vec<(Color, DVec3)> {
+        vec![(
+            self.get_intensity(point, scene, light_index),
+            self.get_direction(point),
+        )]
+    }
+
     fn generate_photon_dir(&self) -> Ray;
     fn generate_caustic_dir(&self, bounds: (DVec3, DVec3)) -> Ray;
 
+    // Generates a photon ray with its color
+    // Lights that are not the same color in every direction change the color
+    fn generate_photon(&self) -> (Ray, Color) {
+        (self.generate_photon_dir(), self.get_color())
+    }
+
+    fn generate_caustic(&self, bounds: (DVec3, DVec3)) -> (Ray, Color) {
+        (self.generate_caustic_dir(bounds), self.get_color())
+    }
+
     fn get_color(&self) -> Color;
 }
@@ -0,0 +1,165 @@
+use crate::color::Color;
+use crate::constants::SHADOW_EPSILON;
+use crate::fibonacci_spiral::cosine_hemisphere_random;
+use crate::lights::falloff::Falloff;
+use crate::lights::light::Light;
+use crate::objects::object::Object;
+use crate::primitives::primitive::Primitive;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+
+// Number of surface points averaged to find the center of the light
+const CENTER_SAMPLES: usize = 1000;
+
+// A light emitted from the surface of an object
+// Used alongside an EmissiveMaterial on the same object so it is both visible and
+//  lights the scene. The color is the total light given out by the object,
+//  the same as the color of a point light
+// Only bounded primitives (triangles and spheres) can emit light, and for CSG objects
+//  the surfaces removed by the CSG still emit
+#[derive(Debug)]
+pub struct ObjectLight {
+    primitives: Vec<Box<dyn Primitive + Sync + Send>>,
+    // Running total of the surface areas, used to pick primitives by area
+    cumulative_areas: Vec<f64>,
+    center: DVec3,
+    color: Color,
+    falloff: Falloff,
+    num_samples: usize,
+}
+
+impl ObjectLight {
+    // Object light with the original (1 + d / 10)^2 falloff
+    // Takes the object in its final position, before it is given to the scene
+    // Note this can fail! The object needs a bounded surface to emit from
+    pub fn new(object: &dyn Object, color: Color, num_samples: usize) -> Result<Self, String> {
+        Self::new_with_falloff(object, color, num_samples, Falloff::legacy())
+    }
+
+    pub fn new_with_falloff(
+        object: &dyn Object,
+        color: Color,
+        num_samples: usize,
+        falloff: Falloff,
+    ) -> Result<Self, String> {
+        let primitives: Vec<Box<dyn Primitive + Sync + Send>> = object
+            .primitives(0)
+            .into_iter()
+            .filter(|p| p.surface_area() > 0.)
+            .collect();
+
+        if primitives.is_empty() {
+            return Err("Object light has no bounded surface to emit from.".to_string());
+        }
+
+        let cumulative_areas = primitives
+            .iter()
+            .scan(0., |total, p| {
+                *total += p.surface_area();
+                Some(*total)
+            })
+            .collect::<Vec<f64>>();
+
+        // Area weighted center of the surface, used as the direction of the light
+        let center = (0..CENTER_SAMPLES)
+            .map(|_| Self::sample(&primitives, &cumulative_areas).0)
+            .sum::<DVec3>()
+            / CENTER_SAMPLES as f64;
+
+        Ok(Self {
+            primitives,
+            cumulative_areas,
+            center,
+            color,
+            falloff,
+            num_samples: num_samples.max(1),
+        })
+    }
+
+    // Picks a random point on the surface, choosing primitives proportional to their area
+    fn sample(
+        primitives: &[Box<dyn Primitive + Sync + Send>],
+        cumulative_areas: &[f64],
+    ) -> (DVec3, DVec3) {
+        let mut rng = rand::thread_rng();
+        let i: f64 = rng.gen_range(0. ..*cumulative_areas.last().unwrap());
+        let index = cumulative_areas.partition_point(|a| *a < i);
+
+        primitives[index.min(primitives.len() - 1)]
+            .sample_surface()
+            .unwrap()
+    }
+
+    fn sample_surface(&self) -> (DVec3, DVec3) {
+        Self::sample(&self.primitives, &self.cumulative_areas)
+    }
+}
+
+impl Light for ObjectLight {
+    fn get_intensity(&self, point: DVec3, scene: &Scene, light_index: usize) -> Color {
+        self.get_samples(point, scene, light_index)
+            .into_iter()
+            .map(|(c, _)| c)
+            .sum()
+    }
+
+    fn get_direction(&self, point: DVec3) -> DVec3 {
+        (point - self.center).normalize()
+    }
+
+    fn get_samples(&self, point: DVec3, scene: &Scene, _: usize) -> Vec<(Color, DVec3)> {
+        (0..self.num_samples)
+            .filter_map(|_| {
+                let (pos, normal) = self.sample_surface();
+                let dir = (point - pos).normalize();
+
+                // Surfaces only emit light outwards
+                let facing = normal.dot(dir);
+                if facing <= 0. {
+                    return None;
+                }
+
+                let transmittance = scene.transmittance(pos + normal * SHADOW_EPSILON, point);
+                if transmittance.max_val() <= 0. {
+                    return None;
+                }
+
+                let distance = point.distance(pos);
+                Some((
+                    self.color.piecewise_mul(&transmittance)
+                        * (facing * self.falloff.attenuation(distance) / self.num_samples as f64),
+                    dir,
+                ))
+            })
+            .collect()
+    }
+
+    fn generate_photon_dir(&self) -> Ray {
+        let (pos, normal) = self.sample_surface();
+        Ray::new(
+            pos + normal * SHADOW_EPSILON,
+            cosine_hemisphere_random(normal),
+        )
+    }
+
+    fn generate_caustic_dir(&self, bounds: (DVec3, DVec3)) -> Ray {
+        if !(bounds.0.is_finite() && bounds.1.is_finite()) {
+            panic!("Non finite bounds, you may need to wrap an object in a CSG.");
+        }
+
+        let (pos, normal) = self.sample_surface();
+
+        let mut rng = rand::thread_rng();
+        let x_rand: f64 = rng.gen_range((bounds.0.x)..bounds.1.x);
+        let y_rand: f64 = rng.gen_range((bounds.0.y)..bounds.1.y);
+        let z_rand: f64 = rng.gen_range((bounds.0.z)..bounds.1.z);
+        let pos = pos + normal * SHADOW_EPSILON;
+        Ray::new(pos, DVec3::new(x_rand, y_rand, z_rand) - pos)
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+}
@@ -1,29 +1,48 @@
 use crate::color::Color;
 use crate::fibonacci_spiral::fibonacci_spiral_random;
+use crate::lights::falloff::Falloff;
 use crate::lights::light::Light;
 use crate::ray::Ray;
 use crate::scene::Scene;
 use glam::DVec3;
 use rand::Rng;
-
-const EPSILON: f64 = 0.01;
+use std::f64::consts::PI;
 
 #[derive(Debug)]
 pub struct PointLight {
     position: DVec3,
     color: Color,
+    falloff: Falloff,
 }
 
 impl PointLight {
+    // Point light with the original (1 + d / 10)^2 falloff
     pub fn new(position: DVec3, color: Color) -> Self {
-        Self { position, color }
+        Self::new_with_falloff(position, color, Falloff::legacy())
+    }
+
+    pub fn new_with_falloff(position: DVec3, color: Color, falloff: Falloff) -> Self {
+        Self {
+            position,
+            color,
+            falloff,
+        }
+    }
+
+    // Point light giving out a total power (in watts) spread evenly in every direction
+    // Uses inverse square falloff so the light is the same however the scene is scaled
+    pub fn from_power(position: DVec3, color: Color, power: f64) -> Self {
+        Self::new_with_falloff(
+            position,
+            color * (power / (4. * PI)),
+            Falloff::InverseSquare,
+        )
     }
 }
 
 impl Light for PointLight {
     fn get_intensity(&self, point: DVec3, scene: &Scene, light_index: usize) -> Color {
         let distance = point.distance(self.position);
-        let ray = Ray::new(self.position, (point - self.position).normalize());
 
         // If there are shadow photons and no direct photons, then it is dark,
         // And vice versa
@@ -38,15 +57,15 @@ impl Light for PointLight {
             .filter(|p| p.is_shadow() && p.get_light_index() == light_index)
             .count();
 
-        if num_shadow > 2 && num_direct == 0
-            || (!(num_direct > 2 && num_shadow == 0)
-                && scene.intersection(ray).any(|r| {
-                    r.get_dir() && r.get_distance() > 0. && r.get_distance() < distance - EPSILON
-                }))
-        {
+        if num_shadow > 2 && num_direct == 0 {
             Color::new_black()
+        } else if num_direct > 2 && num_shadow == 0 {
+            self.color * self.falloff.attenuation(distance)
         } else {
-            self.color * (1. / ((1. + distance / 10.) * (1. + distance / 10.)))
+            // The shadow ray is tinted by any transparent objects it passes through
+            self.color
+                .piecewise_mul(&scene.transmittance(self.position, point))
+                * self.falloff.attenuation(distance)
         }
     }
 
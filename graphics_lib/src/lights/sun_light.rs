@@ -0,0 +1,78 @@
+use crate::color::Color;
+use crate::constants::SCENE_BOUNDS;
+use crate::lights::light::Light;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+use std::f64::consts::PI;
+
+// A light infinitely far away, shining in one direction with no falloff
+// The center and radius should contain the whole scene, photons are fired from
+//  outside this sphere
+#[derive(Debug)]
+pub struct SunLight {
+    // The direction the light travels in
+    direction: DVec3,
+    color: Color,
+    center: DVec3,
+    radius: f64,
+}
+
+impl SunLight {
+    pub fn new(direction: DVec3, color: Color, center: DVec3, radius: f64) -> Self {
+        Self {
+            direction: direction.normalize(),
+            color,
+            center,
+            radius,
+        }
+    }
+}
+
+impl Light for SunLight {
+    fn get_intensity(&self, point: DVec3, scene: &Scene, _: usize) -> Color {
+        self.color.piecewise_mul(
+            &scene.transmittance(point, point - self.direction * SCENE_BOUNDS as f64),
+        )
+    }
+
+    fn get_direction(&self, _: DVec3) -> DVec3 {
+        self.direction
+    }
+
+    fn generate_photon_dir(&self) -> Ray {
+        // Fire from a random point on a disc facing the scene, outside the scene
+        let mut rng = rand::thread_rng();
+        let r: f64 = rng.gen_range::<f64, _>(0. ..1.).sqrt() * self.radius;
+        let theta: f64 = rng.gen_range(0. ..2. * PI);
+        let (a, b) = self.direction.any_orthonormal_pair();
+
+        Ray::new(
+            self.center - self.direction * self.radius
+                + a * (r * theta.cos())
+                + b * (r * theta.sin()),
+            self.direction,
+        )
+    }
+
+    fn generate_caustic_dir(&self, bounds: (DVec3, DVec3)) -> Ray {
+        if !(bounds.0.is_finite() && bounds.1.is_finite()) {
+            panic!("Non finite bounds, you may need to wrap an object in a CSG.");
+        }
+
+        let mut rng = rand::thread_rng();
+        let x_rand: f64 = rng.gen_range((bounds.0.x)..bounds.1.x);
+        let y_rand: f64 = rng.gen_range((bounds.0.y)..bounds.1.y);
+        let z_rand: f64 = rng.gen_range((bounds.0.z)..bounds.1.z);
+
+        Ray::new(
+            DVec3::new(x_rand, y_rand, z_rand) - self.direction * 2. * self.radius,
+            self.direction,
+        )
+    }
+
+    fn get_color(&self) -> Color {
+        self.color
+    }
+}
@@ -1,8 +1,19 @@
 mod ambient_material;
+pub mod blend_weight;
+pub mod bump_material;
 pub mod compound_material;
+pub mod conductor_material;
 pub mod diffuse_material;
+pub mod emissive_material;
 pub mod false_color_material;
+pub mod layered_material;
 pub mod material;
+pub mod material_library;
+pub mod microfacet_material;
 mod reflective_material;
+pub mod refractive_index;
 mod specular_material;
+pub mod subsurface_material;
+pub mod thin_sheet_material;
 mod transparent_material;
+pub mod volume_material;
@@ -59,7 +59,14 @@ impl Material for AmbientMaterial {
 
         // Divide the photon map light intensity by the square root of the number of
         //  photons, this softens the noise
-        photon_map_col * (1. / (photons.len() as f64).sqrt()) + caustic_part * CAUSTIC_SCALAR
+        // With no photons nearby there is no indirect light
+        let photon_map_col = if photons.is_empty() {
+            Color::new_black()
+        } else {
+            photon_map_col * (1. / (photons.len() as f64).sqrt())
+        };
+
+        photon_map_col + caustic_part * CAUSTIC_SCALAR
     }
 
     fn compute_photon(
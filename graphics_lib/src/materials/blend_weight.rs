@@ -0,0 +1,132 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::microfacet::schlick_fresnel;
+use crate::textures::texture::Texture;
+use glam::DVec3;
+use std::sync::Arc;
+
+// How much of a child material of a CompoundMaterial is used at a point on a surface
+// Weights are found for each hit and scaled so they add to 1, so a material can
+//  change over its surface, eg. rusty in patches or mossy on top
+#[derive(Debug, Clone)]
+pub enum BlendWeight {
+    // The same everywhere
+    Constant(f64),
+    // The brightness of a texture, shared so the same pattern can weight several
+    //  child materials
+    Texture(Arc<dyn Texture + Sync + Send>),
+    // 1 where the surface faces the viewer, falling to 0 at grazing angles
+    FacingRatio,
+    // The reflectance of a surface with a refractive index, low facing the viewer and
+    //  rising to 1 at grazing angles
+    Fresnel(f64),
+    // Rises smoothly from 0 to 1 between two distances along an axis, eg. heights
+    //  when the axis is up
+    Height { axis: DVec3, start: f64, end: f64 },
+    // How much the surface faces a direction, 0 facing away or side on, eg. up for
+    //  surfaces where moss or snow would settle
+    Orientation(DVec3),
+    // One minus another weight
+    Inverted(Box<BlendWeight>),
+    // Two weights multiplied together, eg. moss in patches on top facing surfaces
+    Product(Box<BlendWeight>, Box<BlendWeight>),
+}
+
+impl BlendWeight {
+    pub fn inverted(self) -> Self {
+        BlendWeight::Inverted(Box::new(self))
+    }
+
+    pub fn times(self, other: BlendWeight) -> Self {
+        BlendWeight::Product(Box::new(self), Box::new(other))
+    }
+
+    // The weight at a hit, given the cosine of the angle between the normal and the
+    //  direction to the viewer
+    pub(crate) fn get_weight(&self, hit: &Hit, cos_view: f64) -> f64 {
+        match self {
+            BlendWeight::Constant(w) => *w,
+            BlendWeight::Texture(texture) => texture.get_color(hit).magnitude(),
+            BlendWeight::FacingRatio => cos_view.abs().min(1.),
+            BlendWeight::Fresnel(refractive_index) => {
+                let f0 = ((refractive_index - 1.) / (refractive_index + 1.)).powi(2);
+                schlick_fresnel(Color::new_grey(f0), cos_view.abs()).magnitude()
+            }
+            BlendWeight::Height { axis, start, end } => {
+                let t = ((hit.pos().dot(axis.normalize()) - start) / (end - start)).clamp(0., 1.);
+                t * t * (3. - 2. * t)
+            }
+            BlendWeight::Orientation(direction) => hit.normal().dot(direction.normalize()).max(0.),
+            BlendWeight::Inverted(weight) => 1. - weight.get_weight(hit, cos_view),
+            BlendWeight::Product(a, b) => a.get_weight(hit, cos_view) * b.get_weight(hit, cos_view),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    // A hit on a surface facing up at a height of 1
+    fn hit() -> Hit {
+        Hit::new(DVec3::new(0., 1., 0.), DVec3::Y, 1., true, 0, 0)
+    }
+
+    fn weight(weight: BlendWeight, cos_view: f64) -> f64 {
+        weight.get_weight(&hit(), cos_view)
+    }
+
+    #[test]
+    fn constant_and_texture() {
+        assert_eq!(weight(BlendWeight::Constant(0.3), 1.), 0.3);
+        let texture = BlendWeight::Texture(Arc::new(Color::new_grey(0.25)));
+        assert!((weight(texture, 1.) - 0.25).abs() < 1E-12);
+    }
+
+    #[test]
+    fn facing_ratio() {
+        assert_eq!(weight(BlendWeight::FacingRatio, 1.), 1.);
+        assert_eq!(weight(BlendWeight::FacingRatio, -0.5), 0.5);
+        assert_eq!(weight(BlendWeight::FacingRatio, 0.), 0.);
+    }
+
+    #[test]
+    fn fresnel_rises_at_grazing_angles() {
+        assert!((weight(BlendWeight::Fresnel(1.5), 1.) - 0.04).abs() < 1E-12);
+        assert!((weight(BlendWeight::Fresnel(1.5), 0.) - 1.).abs() < 1E-12);
+        assert!(weight(BlendWeight::Fresnel(1.5), 0.5) < weight(BlendWeight::Fresnel(1.5), 0.2));
+    }
+
+    #[test]
+    fn height_is_smooth_between_start_and_end() {
+        let height = |start: f64, end: f64| {
+            weight(
+                BlendWeight::Height {
+                    axis: DVec3::Y * 2.,
+                    start,
+                    end,
+                },
+                1.,
+            )
+        };
+        assert_eq!(height(2., 3.), 0.);
+        assert_eq!(height(-1., 0.), 1.);
+        assert!((height(0., 2.) - 0.5).abs() < 1E-12);
+    }
+
+    #[test]
+    fn orientation() {
+        assert!((weight(BlendWeight::Orientation(DVec3::Y * 3.), 1.) - 1.).abs() < 1E-12);
+        assert_eq!(weight(BlendWeight::Orientation(-DVec3::Y), 1.), 0.);
+        assert_eq!(weight(BlendWeight::Orientation(DVec3::X), 1.), 0.);
+    }
+
+    #[test]
+    fn inverted_and_product() {
+        let inverted = BlendWeight::Constant(0.3).inverted();
+        assert!((weight(inverted, 1.) - 0.7).abs() < 1E-12);
+        let product = BlendWeight::Constant(0.5).times(BlendWeight::FacingRatio);
+        assert!((weight(product, 0.5) - 0.25).abs() < 1E-12);
+    }
+}
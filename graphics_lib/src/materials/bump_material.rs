@@ -0,0 +1,179 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::textures::texture::Texture;
+use glam::{DVec2, DVec3};
+
+// Distance moved along the surface to find how fast a height map changes
+const BUMP_DELTA: f64 = 1.0E-3;
+
+// How the shading normal is changed
+#[derive(Debug)]
+enum NormalPerturbation {
+    // The brightness of the texture is a height above the surface
+    Bump(Box<dyn Texture + Sync + Send>),
+    // The texture is a tangent space normal, red along u, green up the image
+    //  (against v) and blue out of the surface, each mapped from 0..1 to -1..1
+    NormalMap(Box<dyn Texture + Sync + Send>),
+}
+
+// Wraps another material, perturbing the normal of each hit before passing it on
+// This gives surfaces detail such as bumps and grooves without extra geometry
+// The strength scales how much the normal is moved, 0 leaves it unchanged
+#[derive(Debug)]
+pub struct BumpMaterial {
+    material: Box<dyn Material + Sync + Send>,
+    perturbation: NormalPerturbation,
+    strength: f64,
+}
+
+impl BumpMaterial {
+    // Bump mapping from a height map, where brighter is higher
+    // Works with both image and solid textures
+    pub fn new_bump(
+        material: Box<dyn Material + Sync + Send>,
+        height: Box<dyn Texture + Sync + Send>,
+        strength: f64,
+    ) -> Self {
+        BumpMaterial {
+            material,
+            perturbation: NormalPerturbation::Bump(height),
+            strength,
+        }
+    }
+
+    // Normal mapping from a tangent space normal map
+    // Normal maps should be loaded without squaring, see ImageTexture::from_linear_ppm_file
+    pub fn new_normal_map(
+        material: Box<dyn Material + Sync + Send>,
+        normal_map: Box<dyn Texture + Sync + Send>,
+        strength: f64,
+    ) -> Self {
+        BumpMaterial {
+            material,
+            perturbation: NormalPerturbation::NormalMap(normal_map),
+            strength,
+        }
+    }
+
+    // The hit with its normal perturbed
+    fn perturb(&self, hit: &Hit) -> Hit {
+        let normal = *hit.normal();
+        let tangent = hit.tangent();
+        let bitangent = hit.bitangent();
+
+        let new_normal = match &self.perturbation {
+            NormalPerturbation::Bump(height) => {
+                // Moves along the surface in both position and texture coordinates, so
+                //  solid textures and image textures both change
+                let base = height.get_color(hit).magnitude();
+                let du = (height
+                    .get_color(&hit.moved(tangent * BUMP_DELTA, DVec2::new(BUMP_DELTA, 0.)))
+                    .magnitude()
+                    - base)
+                    / BUMP_DELTA;
+                let dv = (height
+                    .get_color(&hit.moved(bitangent * BUMP_DELTA, DVec2::new(0., BUMP_DELTA)))
+                    .magnitude()
+                    - base)
+                    / BUMP_DELTA;
+
+                // Blinn's bump mapping, tilts the normal away from the slope
+                normal + (normal.cross(bitangent) * du + tangent.cross(normal) * dv) * self.strength
+            }
+            NormalPerturbation::NormalMap(normal_map) => {
+                let color = normal_map.get_color(hit);
+                let local = DVec3::new(
+                    (color.red() * 2. - 1.) * self.strength,
+                    (color.green() * 2. - 1.) * self.strength,
+                    color.blue() * 2. - 1.,
+                );
+                tangent * local.x - bitangent * local.y + normal * local.z
+            }
+        };
+
+        // Normals bent behind the surface would light it from the wrong side
+        match new_normal.try_normalize() {
+            Some(n) if n.dot(normal) > 0. => hit.clone().with_normal(n),
+            _ => hit.clone(),
+        }
+    }
+}
+
+impl Material for BumpMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        ambient: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        self.material.compute(
+            view_ray,
+            &self.perturb(hit),
+            ambient,
+            scene,
+            recurse_depth,
+            recurse_power,
+        )
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        self.material.compute_photon(
+            view_ray,
+            &self.perturb(hit),
+            scene,
+            recurse_depth,
+            recurse_power,
+            light_index,
+        )
+    }
+
+    fn needs_caustic(&self) -> bool {
+        self.material.needs_caustic()
+    }
+
+    fn shadow_transmittance(&self, hit: &Hit) -> Color {
+        self.material.shadow_transmittance(hit)
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.material.is_transparent()
+    }
+
+    fn is_two_sided(&self) -> bool {
+        self.material.is_two_sided()
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        light_index: usize,
+        color: Color,
+    ) -> Option<Photon> {
+        self.material.compute_caustic_ray(
+            view_ray,
+            &self.perturb(hit),
+            scene,
+            recurse_depth,
+            light_index,
+            color,
+        )
+    }
+}
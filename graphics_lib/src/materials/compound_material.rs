@@ -2,44 +2,72 @@ use crate::color::Color;
 use crate::constants::DEFAULT_AMBIENT;
 use crate::hit::Hit;
 use crate::materials::ambient_material::AmbientMaterial;
+use crate::materials::blend_weight::BlendWeight;
 use crate::materials::diffuse_material::DiffuseMaterial;
 use crate::materials::material::Material;
 use crate::materials::reflective_material::ReflectiveMaterial;
+use crate::materials::refractive_index::RefractiveIndex;
 use crate::materials::specular_material::SpecularMaterial;
 use crate::materials::transparent_material::TransparentMaterial;
 use crate::photon::Photon;
 use crate::ray::Ray;
 use crate::scene::Scene;
+use crate::textures::texture::Texture;
 use rand::Rng;
 use std::fmt::Debug;
 
 // This material represents several other materials, each with their own
 //      weighting that are applied and summed together
+// Weights can vary over the surface, see BlendWeight
+// The result is tinted by a color, or a texture that varies over the surface
 #[derive(Debug)]
 pub struct CompoundMaterial {
-    color: Color,
-    materials: Vec<(Box<dyn Material + Sync + Send>, f64)>,
+    color: Box<dyn Texture + Sync + Send>,
+    materials: Vec<(Box<dyn Material + Sync + Send>, BlendWeight)>,
 }
 
 impl CompoundMaterial {
     pub fn new(materials: Vec<(Box<dyn Material + Sync + Send>, f64)>, color: Color) -> Self {
-        // Scales the weights to they add to 1
-        let scale = 1. / materials.iter().fold(0., |ct, (_, weight)| ct + weight);
+        CompoundMaterial::new_textured(materials, Box::new(color))
+    }
 
-        CompoundMaterial {
-            color,
-            materials: materials
+    pub fn new_textured(
+        materials: Vec<(Box<dyn Material + Sync + Send>, f64)>,
+        texture: Box<dyn Texture + Sync + Send>,
+    ) -> Self {
+        CompoundMaterial::new_blended(
+            materials
                 .into_iter()
-                .map(|(m, w)| (m, (w * scale)))
+                .map(|(m, w)| (m, BlendWeight::Constant(w)))
                 .collect(),
+            texture,
+        )
+    }
+
+    // Blends child materials with weights that can change over the surface
+    pub fn new_blended(
+        materials: Vec<(Box<dyn Material + Sync + Send>, BlendWeight)>,
+        texture: Box<dyn Texture + Sync + Send>,
+    ) -> Self {
+        CompoundMaterial {
+            color: texture,
+            materials,
         }
     }
 
     // Helper constructor to make a sensible matte material
     pub fn new_matte_material(col: Color, specular: f64) -> CompoundMaterial {
+        CompoundMaterial::new_textured_matte_material(Box::new(col), specular)
+    }
+
+    // Helper constructor to make a matte material colored by a texture
+    pub fn new_textured_matte_material(
+        texture: Box<dyn Texture + Sync + Send>,
+        specular: f64,
+    ) -> CompoundMaterial {
         assert!(1. >= specular);
         assert!(0. <= specular);
-        CompoundMaterial::new(
+        CompoundMaterial::new_textured(
             vec![
                 (Box::new(AmbientMaterial::new()), DEFAULT_AMBIENT),
                 (
@@ -51,12 +79,38 @@ impl CompoundMaterial {
                     (1. - DEFAULT_AMBIENT) * (specular),
                 ),
             ],
+            texture,
+        )
+    }
+
+    // Helper constructor to make a rough matte material, like clay, plaster or stone
+    // Roughness is the spread of the angles of the surface in radians, where 0 is
+    //  the same as a matte material with no specular
+    pub fn new_rough_matte_material(col: Color, roughness: f64) -> CompoundMaterial {
+        CompoundMaterial::new(
+            vec![
+                (Box::new(AmbientMaterial::new()), DEFAULT_AMBIENT),
+                (
+                    Box::new(DiffuseMaterial::new_rough(roughness)),
+                    1. - DEFAULT_AMBIENT,
+                ),
+            ],
             col,
         )
     }
 
     // Helper constructor to make a sensible reflective material
     pub fn new_reflective_material(col: Color, reflectivity: f64) -> CompoundMaterial {
+        CompoundMaterial::new_rough_reflective_material(col, reflectivity, 0.)
+    }
+
+    // Helper constructor to make a reflective material with a blurry reflection
+    // Roughness goes from 0 (a mirror) to 1 (very blurry)
+    pub fn new_rough_reflective_material(
+        col: Color,
+        reflectivity: f64,
+        roughness: f64,
+    ) -> CompoundMaterial {
         assert!(1. >= reflectivity);
         assert!(0. <= reflectivity);
         CompoundMaterial::new(
@@ -68,14 +122,62 @@ impl CompoundMaterial {
                     )),
                     (1. - reflectivity),
                 ),
-                (Box::new(ReflectiveMaterial::new()), (reflectivity)),
+                (Box::new(ReflectiveMaterial::new(roughness)), (reflectivity)),
             ],
             col,
         )
     }
 
+    // Helper constructor to make a glossy dielectric, like plastic or polished stone
+    // The reflection is weighted by the Fresnel equations for the refractive index, so
+    //  it is faint looking straight on and a mirror at grazing angles
+    // Only the matte base is colored, the reflection stays white
+    // The base has no specular of its own, so the Fresnel reflection is the only shine
+    pub fn new_fresnel_reflective_material(
+        col: Color,
+        refractive_index: f64,
+        roughness: f64,
+    ) -> CompoundMaterial {
+        assert!(refractive_index >= 1.);
+        CompoundMaterial::new_blended(
+            vec![
+                (
+                    Box::new(CompoundMaterial::new_rough_matte_material(col, 0.)),
+                    BlendWeight::Fresnel(refractive_index).inverted(),
+                ),
+                (
+                    Box::new(ReflectiveMaterial::new(roughness)),
+                    BlendWeight::Fresnel(refractive_index),
+                ),
+            ],
+            Box::new(Color::new_grey(1.)),
+        )
+    }
+
     // Helper constructor to make a sensible transparent material
     pub fn new_transparent_material(refractive_index: f64) -> CompoundMaterial {
+        CompoundMaterial::new_frosted_transparent_material(refractive_index, 0.)
+    }
+
+    // Helper constructor to make a transparent material with a rough surface, like
+    //  frosted glass
+    // Roughness goes from 0 (clear) to 1 (very frosted)
+    pub fn new_frosted_transparent_material(
+        refractive_index: f64,
+        roughness: f64,
+    ) -> CompoundMaterial {
+        CompoundMaterial::new_absorbing_transparent_material(
+            refractive_index,
+            roughness,
+            Color::new_black(),
+        )
+    }
+
+    // Helper constructor to make a transparent material that splits light into
+    //  colors, like a prism or cut crystal
+    pub fn new_dispersive_transparent_material(
+        refractive_index: RefractiveIndex,
+    ) -> CompoundMaterial {
         CompoundMaterial::new(
             vec![
                 (
@@ -86,7 +188,69 @@ impl CompoundMaterial {
                     0.1,
                 ),
                 (
-                    Box::new(TransparentMaterial::new(refractive_index)),
+                    Box::new(TransparentMaterial::new(1.).with_dispersion(refractive_index)),
+                    1. - 0.1,
+                ),
+            ],
+            Color::new_grey(1.),
+        )
+    }
+
+    // Helper constructor to make a transparent material that absorbs light passing
+    //  through it, like colored glass or liquids
+    // The absorption is the fraction of each color absorbed per unit distance, so
+    //  Color::new(0.1, 0.5, 0.8) gives a deepening red
+    pub fn new_absorbing_transparent_material(
+        refractive_index: f64,
+        roughness: f64,
+        absorption: Color,
+    ) -> CompoundMaterial {
+        CompoundMaterial::new(
+            vec![
+                (
+                    Box::new(CompoundMaterial::new_matte_material(
+                        Color::new_grey(1.),
+                        0.1,
+                    )),
+                    0.1,
+                ),
+                (
+                    Box::new(
+                        TransparentMaterial::new_rough(refractive_index, roughness)
+                            .with_absorption(absorption),
+                    ),
+                    1. - 0.1,
+                ),
+            ],
+            Color::new_grey(1.),
+        )
+    }
+
+    // Helper constructor to make a transparent material for objects that overlap other
+    //  transparent objects
+    // Where objects overlap the one with the higher priority fills the overlap, so a
+    //  glass given a higher priority than the liquid inside it can have the liquid
+    //  slightly overlap its walls to avoid a gap of air between them
+    pub fn new_nested_transparent_material(
+        refractive_index: f64,
+        absorption: Color,
+        priority: u32,
+    ) -> CompoundMaterial {
+        CompoundMaterial::new(
+            vec![
+                (
+                    Box::new(CompoundMaterial::new_matte_material(
+                        Color::new_grey(1.),
+                        0.1,
+                    )),
+                    0.1,
+                ),
+                (
+                    Box::new(
+                        TransparentMaterial::new(refractive_index)
+                            .with_absorption(absorption)
+                            .with_priority(priority),
+                    ),
                     1. - 0.1,
                 ),
             ],
@@ -119,6 +283,36 @@ impl CompoundMaterial {
             color,
         )
     }
+
+    // The weight of each child material at a hit, given the cosine of the angle
+    //  between the normal and the direction to the viewer
+    fn weights(&self, hit: &Hit, cos_view: f64) -> Vec<f64> {
+        let weights = self
+            .materials
+            .iter()
+            .map(|(_, w)| w.get_weight(hit, cos_view).max(0.))
+            .collect::<Vec<f64>>();
+
+        // Scales the weights to they add to 1
+        let total = weights.iter().sum::<f64>();
+        if total <= 0. {
+            return vec![0.; weights.len()];
+        }
+        weights.into_iter().map(|w| w / total).collect()
+    }
+
+    // The transparent child material, used alone for rays inside transparent objects
+    // The rest of the material is a finish only seen from the air outside the object
+    fn inside_material(&self, view_ray: &Ray, hit: &Hit) -> Option<&(dyn Material + Sync + Send)> {
+        if hit.get_dir() && view_ray.media().is_empty() {
+            return None;
+        }
+
+        self.materials
+            .iter()
+            .find(|(m, _)| m.is_transparent())
+            .map(|(m, _)| m.as_ref())
+    }
 }
 
 impl Material for CompoundMaterial {
@@ -131,10 +325,20 @@ impl Material for CompoundMaterial {
         recurse_depth: usize,
         recurse_power: Color,
     ) -> Color {
+        if let Some(m) = self.inside_material(&view_ray, hit) {
+            return m.compute(view_ray, hit, ambient, scene, recurse_depth, recurse_power);
+        }
+
+        let color = self.color.get_color(hit);
+        let weights = self.weights(hit, hit.normal().dot(-view_ray.direction()));
+
         // Computes for each child material and sums them multiplied by their weights
+        // Children with no weight at this point are skipped
         self.materials
             .iter()
-            .fold(Color::new_black(), |tc, (m, w)| {
+            .zip(weights)
+            .filter(|(_, w)| *w > 0.)
+            .fold(Color::new_black(), |tc, ((m, _), w)| {
                 tc + m
                     .compute(
                         view_ray,
@@ -142,10 +346,10 @@ impl Material for CompoundMaterial {
                         ambient,
                         scene,
                         recurse_depth,
-                        recurse_power.piecewise_mul(&self.color) * *w,
+                        recurse_power.piecewise_mul(&color) * w,
                     )
-                    .piecewise_mul(&self.color)
-                    * *w
+                    .piecewise_mul(&color)
+                    * w
             })
     }
 
@@ -158,15 +362,27 @@ impl Material for CompoundMaterial {
         recurse_power: Color,
         light_index: usize,
     ) -> Vec<Photon> {
+        if let Some(m) = self.inside_material(&view_ray, hit) {
+            return m.compute_photon(
+                view_ray,
+                hit,
+                scene,
+                recurse_depth,
+                recurse_power,
+                light_index,
+            );
+        }
+
         let mut rng = rand::thread_rng();
         let mut i: f64 = rng.gen_range((0.)..1.);
 
         // Randomly chooses a child material to send the photon from
         // This is the Monte Carlo implementation
+        let weights = self.weights(hit, hit.normal().dot(-view_ray.direction()));
         let mat = {
             let mut res = self.materials.first().unwrap();
-            for mat in self.materials.iter() {
-                i -= mat.1;
+            for (mat, w) in self.materials.iter().zip(weights) {
+                i -= w;
                 if i <= 0. {
                     res = mat;
                     break;
@@ -175,14 +391,20 @@ impl Material for CompoundMaterial {
             res
         };
 
-        let mut res = mat.0.compute_photon(
-            view_ray,
-            hit,
-            scene,
-            recurse_depth,
-            recurse_power.mul_const_mag(&self.color),
-            light_index,
-        );
+        // Black parts of the surface absorb the photon, so it is only recorded here
+        let color = self.color.get_color(hit);
+        let mut res = if color.magnitude() <= 0. {
+            vec![]
+        } else {
+            mat.0.compute_photon(
+                view_ray,
+                hit,
+                scene,
+                recurse_depth,
+                recurse_power.mul_const_mag(&color),
+                light_index,
+            )
+        };
 
         // Also add an indirect photon at this point
         res.push(Photon::new_indirect(
@@ -200,9 +422,33 @@ impl Material for CompoundMaterial {
         self.materials.iter().any(|(m, _)| m.needs_caustic())
     }
 
+    // Returns true if any child material lets light inside
+    fn is_transparent(&self) -> bool {
+        self.materials.iter().any(|(m, _)| m.is_transparent())
+    }
+
+    // Light passes through the transparent parts of the material, tinted by its color
+    // Weights that depend on the view use the light passing straight through
+    fn shadow_transmittance(&self, hit: &Hit) -> Color {
+        self.materials
+            .iter()
+            .zip(self.weights(hit, 1.))
+            .fold(Color::new_black(), |tc, ((m, _), w)| {
+                tc + m.shadow_transmittance(hit) * w
+            })
+            .piecewise_mul(&self.color.get_color(hit))
+    }
+
+    // Both sides can be seen if any child material is two sided
+    fn is_two_sided(&self) -> bool {
+        self.materials.iter().any(|(m, _)| m.is_two_sided())
+    }
+
     // Returns the caustic of only one of the child materials that make caustics
-    // This is because each compound material should have at most one transparent
-    //  child material
+    // A transparent child material always takes the whole caustic, as each compound
+    //  material should have at most one
+    // Otherwise one of the reflective child materials is picked randomly by their
+    //  weights, and the caustic carries their share of the light
     fn compute_caustic_ray(
         &self,
         view_ray: Ray,
@@ -212,19 +458,45 @@ impl Material for CompoundMaterial {
         light_index: usize,
         color: Color,
     ) -> Option<Photon> {
-        // If any sub material retransmits caustics, use that
-        self.materials
+        let color = color.piecewise_mul(&self.color.get_color(hit));
+
+        if let Some((m, _)) = self
+            .materials
+            .iter()
+            .find(|(m, _)| m.is_transparent() && m.needs_caustic())
+        {
+            return m.compute_caustic_ray(view_ray, hit, scene, recurse_depth, light_index, color);
+        }
+
+        let caustic_materials = self
+            .materials
+            .iter()
+            .zip(self.weights(hit, hit.normal().dot(-view_ray.direction())))
+            .filter(|((m, _), w)| m.needs_caustic() && *w > 0.)
+            .map(|((m, _), w)| (m, w))
+            .collect::<Vec<_>>();
+
+        let total = caustic_materials.iter().map(|(_, w)| w).sum::<f64>();
+        if total <= 0. {
+            return None;
+        }
+
+        let mut i: f64 = rand::thread_rng().gen_range(0. ..total);
+        let (mat, _) = caustic_materials
             .iter()
-            .filter_map(|(m, _)| {
-                m.compute_caustic_ray(
-                    view_ray,
-                    hit,
-                    scene,
-                    recurse_depth,
-                    light_index,
-                    color.piecewise_mul(&self.color),
-                )
+            .find(|(_, w)| {
+                i -= w;
+                i <= 0.
             })
-            .next() // this selects only the first caustic from the children
+            .unwrap_or(caustic_materials.last()?);
+
+        mat.compute_caustic_ray(
+            view_ray,
+            hit,
+            scene,
+            recurse_depth,
+            light_index,
+            color * total,
+        )
     }
 }
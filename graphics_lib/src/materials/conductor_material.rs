@@ -0,0 +1,208 @@
+use crate::color::Color;
+use crate::constants::{
+    DEFAULT_GLOSSY_SAMPLES, EPSILON, MAX_CAUSTIC_ROUGHNESS, MAX_PHOTON_RECURSE_DEPTH,
+    MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+};
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::microfacet::{
+    conductor_fresnel, direct_light, ggx_specular, glossy_sample_count, roughness_to_alpha,
+    sample_ggx_reflection,
+};
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+
+// Metals with measured complex indices of refraction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metal {
+    Gold,
+    Silver,
+    Copper,
+    Aluminium,
+    Iron,
+}
+
+impl Metal {
+    // The n and k of the metal for red, green and blue light (650, 550 and 450nm)
+    fn index_of_refraction(&self) -> (DVec3, DVec3) {
+        match self {
+            Metal::Gold => (
+                DVec3::new(0.143, 0.374, 1.442),
+                DVec3::new(3.983, 2.385, 1.603),
+            ),
+            Metal::Silver => (
+                DVec3::new(0.155, 0.117, 0.138),
+                DVec3::new(4.828, 3.122, 2.147),
+            ),
+            Metal::Copper => (
+                DVec3::new(0.200, 0.924, 1.102),
+                DVec3::new(3.912, 2.452, 2.142),
+            ),
+            Metal::Aluminium => (
+                DVec3::new(1.657, 0.880, 0.521),
+                DVec3::new(9.224, 6.270, 4.837),
+            ),
+            Metal::Iron => (
+                DVec3::new(2.912, 2.950, 2.585),
+                DVec3::new(3.089, 2.932, 2.767),
+            ),
+        }
+    }
+}
+
+// This material is a metal, reflecting using the Fresnel equations for conductors
+// The reflection color changes with angle, getting whiter towards grazing angles
+// A roughness above 0 blurs the reflection and spreads out highlights from lights
+#[derive(Debug, Clone)]
+pub struct ConductorMaterial {
+    // Complex index of refraction n + ik for red, green and blue
+    n: DVec3,
+    k: DVec3,
+    roughness: f64,
+}
+
+impl ConductorMaterial {
+    pub fn new(n: DVec3, k: DVec3, roughness: f64) -> Self {
+        assert!((0. ..=1.).contains(&roughness));
+        ConductorMaterial { n, k, roughness }
+    }
+
+    pub fn from_metal(metal: Metal, roughness: f64) -> Self {
+        let (n, k) = metal.index_of_refraction();
+        ConductorMaterial::new(n, k, roughness)
+    }
+
+    // Fraction of light reflected, given the cosine of the angle to the facet
+    fn fresnel(&self, cos_theta: f64) -> Color {
+        conductor_fresnel(self.n, self.k, cos_theta)
+    }
+
+    // Highlights from the lights in the scene
+    fn direct(&self, view: DVec3, hit: &Hit, scene: &Scene) -> Color {
+        let alpha = roughness_to_alpha(self.roughness);
+        direct_light(hit, scene, |light| {
+            ggx_specular(*hit.normal(), view, light, alpha, |cos_theta| {
+                self.fresnel(cos_theta)
+            })
+        })
+    }
+
+    // Picks a reflected direction and its weight, tinted by the Fresnel reflectance
+    fn sample_reflection(&self, view: DVec3, normal: DVec3) -> Option<(DVec3, Color)> {
+        sample_ggx_reflection(
+            view,
+            normal,
+            roughness_to_alpha(self.roughness),
+            |cos_theta| self.fresnel(cos_theta),
+        )
+    }
+}
+
+impl Material for ConductorMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        _: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let view = -view_ray.direction().normalize();
+        let direct = self.direct(view, hit, scene);
+
+        if recurse_depth >= MAX_RECURSE_DEPTH || recurse_power.min_val() <= MIN_RECURSE_COEFFICIENT
+        {
+            return direct;
+        }
+
+        // Smooth metals only need the one perfect reflection
+        let samples = if self.roughness > 0. {
+            glossy_sample_count(DEFAULT_GLOSSY_SAMPLES, recurse_depth)
+        } else {
+            1
+        };
+
+        let reflection = (0..samples)
+            .filter_map(|_| self.sample_reflection(view, *hit.normal()))
+            .map(|(dir, reflectance)| {
+                scene
+                    .calc_ray(
+                        Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                        recurse_power.piecewise_mul(&reflectance),
+                        recurse_depth + 1,
+                    )
+                    .0
+                    .piecewise_mul(&reflectance)
+            })
+            .sum::<Color>()
+            * (1. / samples as f64);
+
+        direct + reflection
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        let Some((dir, reflectance)) =
+            self.sample_reflection(-view_ray.direction().normalize(), *hit.normal())
+        else {
+            return vec![];
+        };
+
+        // The photon survives with the chance of being reflected, so its power only
+        //  changes color, unless the sampling weight takes the reflectance above 1 at
+        //  grazing angles, when the power carries the extra
+        let survival = reflectance.magnitude().min(1.);
+        let mut rng = rand::thread_rng();
+        if survival <= 0. || rng.gen_range(0. ..1.) >= survival {
+            return vec![];
+        }
+
+        scene.calculate_photon_ray(
+            Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+            light_index,
+            recurse_depth,
+            recurse_power.piecewise_mul(&reflectance) * (1. / survival),
+        )
+    }
+
+    // Polished metals focus light into caustics, tinted by the metal
+    fn needs_caustic(&self) -> bool {
+        self.roughness <= MAX_CAUSTIC_ROUGHNESS
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        light_index: usize,
+        color: Color,
+    ) -> Option<Photon> {
+        // If exceeded recurse depth, skip
+        if recurse_depth > MAX_PHOTON_RECURSE_DEPTH {
+            return None;
+        }
+
+        let (dir, reflectance) =
+            self.sample_reflection(-view_ray.direction().normalize(), *hit.normal())?;
+        scene.calculate_caustic(
+            &Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+            hit.get_object_index(),
+            light_index,
+            color.piecewise_mul(&reflectance),
+            recurse_depth,
+        )
+    }
+}
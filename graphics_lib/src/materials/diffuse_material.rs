@@ -1,35 +1,93 @@
 use crate::color::Color;
-use crate::fibonacci_spiral::hemisphere_random;
+use crate::fibonacci_spiral::{cosine_hemisphere_random, hemisphere_random};
 use crate::hit::Hit;
 use crate::materials::material::Material;
 use crate::photon::Photon;
 use crate::ray::Ray;
 use crate::scene::Scene;
+use glam::DVec3;
 
 // This material represents the diffuse lighting of a material
 //      Uses the Phong lighting model
+// A roughness above 0 uses the Oren-Nayar model instead, for rough surfaces like clay,
+//  plaster and stone which look flatter and brighter towards the viewer than Lambert
 #[derive(Debug, Clone)]
-pub struct DiffuseMaterial {}
+pub struct DiffuseMaterial {
+    // Standard deviation of the angle of the surface's facets in radians
+    roughness: f64,
+}
 
 impl DiffuseMaterial {
     pub fn new() -> DiffuseMaterial {
-        DiffuseMaterial {}
+        DiffuseMaterial::new_rough(0.)
+    }
+
+    pub fn new_rough(roughness: f64) -> DiffuseMaterial {
+        assert!(roughness >= 0.);
+        DiffuseMaterial { roughness }
+    }
+
+    // How much brighter or darker the Oren-Nayar model is than Lambert for light
+    //  arriving from one direction and leaving in another, both away from the surface
+    fn oren_nayar(&self, normal: DVec3, to_viewer: DVec3, to_light: DVec3) -> f64 {
+        if self.roughness <= 0. {
+            return 1.;
+        }
+
+        let sigma2 = self.roughness * self.roughness;
+        let a = 1. - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let cos_light = normal.dot(to_light).clamp(0., 1.);
+        let cos_viewer = normal.dot(to_viewer).clamp(0., 1.);
+
+        // Cosine of the angle between the two directions around the normal
+        let cos_phi = match (
+            (to_light - normal * cos_light).try_normalize(),
+            (to_viewer - normal * cos_viewer).try_normalize(),
+        ) {
+            (Some(l), Some(v)) => l.dot(v).max(0.),
+            _ => 0.,
+        };
+
+        // Sine of the larger angle to the normal and tangent of the smaller
+        let sin_light = (1. - cos_light * cos_light).sqrt();
+        let sin_viewer = (1. - cos_viewer * cos_viewer).sqrt();
+        let (sin_alpha, tan_beta) = if cos_light < cos_viewer {
+            (sin_light, sin_viewer / cos_viewer)
+        } else {
+            (sin_viewer, sin_light / cos_light.max(f64::EPSILON))
+        };
+
+        a + b * cos_phi * sin_alpha * tan_beta
+    }
+}
+
+impl Default for DiffuseMaterial {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Material for DiffuseMaterial {
-    fn compute<'a>(&self, _: Ray, hit: &Hit, _: Color, scene: &Scene, _: usize, _: Color) -> Color {
+    fn compute<'a>(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        _: Color,
+        scene: &Scene,
+        _: usize,
+        _: Color,
+    ) -> Color {
         scene
             .get_lights() // For each light in the scene
             .iter()
             .enumerate()
-            .fold(Color::new_black(), |c, (i, light)| {
-                let intensity = light.get_intensity(*hit.pos(), scene, i);
-
-                let dir = light.get_direction(*hit.pos());
-
+            .flat_map(|(i, light)| light.get_samples(*hit.pos(), scene, i))
+            .fold(Color::new_black(), |c, (intensity, dir)| {
                 // this is the diffuse coefficient from the phong lighting model
-                let diffuse = hit.normal().dot(-dir).max(0.);
+                let diffuse = hit.normal().dot(-dir).max(0.)
+                    * self.oren_nayar(*hit.normal(), -view_ray.direction(), -dir);
 
                 // Sum it with the intensity from other lights
                 c + intensity * diffuse
@@ -38,7 +96,7 @@ impl Material for DiffuseMaterial {
 
     fn compute_photon(
         &self,
-        _: Ray,
+        view_ray: Ray,
         hit: &Hit,
         scene: &Scene,
         recurse_depth: usize,
@@ -46,12 +104,19 @@ impl Material for DiffuseMaterial {
         light_index: usize,
     ) -> Vec<Photon> {
         // New photon in random direction (acute angle to normal)
-        let dir = hemisphere_random(*hit.normal());
+        // Rough surfaces pick directions following the Lambert cosine instead, then
+        //  weight them by how much brighter or darker Oren-Nayar is than Lambert, so
+        //  photons scattered back towards where they came from carry more power
+        let dir = if self.roughness > 0. {
+            cosine_hemisphere_random(*hit.normal())
+        } else {
+            hemisphere_random(*hit.normal())
+        };
         scene.calculate_photon_ray(
-            Ray::new(*hit.pos(), dir),
+            Ray::new(*hit.pos(), dir).with_media(view_ray.media()),
             light_index,
             recurse_depth,
-            recurse_power,
+            recurse_power * self.oren_nayar(*hit.normal(), dir, -view_ray.direction()),
         )
     }
 
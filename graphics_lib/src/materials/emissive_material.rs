@@ -0,0 +1,55 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+
+// This material emits light of its own, regardless of the lights in the scene
+// To also light up the rest of the scene, the object should be added as an ObjectLight
+#[derive(Debug, Clone)]
+pub struct EmissiveMaterial {
+    color: Color,
+}
+
+impl EmissiveMaterial {
+    pub fn new(color: Color) -> Self {
+        EmissiveMaterial { color }
+    }
+}
+
+impl Material for EmissiveMaterial {
+    fn compute(&self, _: Ray, _: &Hit, _: Color, _: &Scene, _: usize, _: Color) -> Color {
+        self.color
+    }
+
+    fn compute_photon(
+        &self,
+        _: Ray,
+        _: &Hit,
+        _: &Scene,
+        _: usize,
+        _: Color,
+        _: usize,
+    ) -> Vec<Photon> {
+        // Light sources absorb photons
+        vec![]
+    }
+
+    fn needs_caustic(&self) -> bool {
+        false
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        _view_ray: Ray,
+        _hit: &Hit,
+        _scene: &Scene,
+        _recurse_depth: usize,
+        _light_index: usize,
+        _: Color,
+    ) -> Option<Photon> {
+        // Does not retransmit caustics
+        None
+    }
+}
@@ -0,0 +1,276 @@
+use crate::color::Color;
+use crate::constants::{
+    DEFAULT_GLOSSY_SAMPLES, EPSILON, MAX_CAUSTIC_ROUGHNESS, MAX_PHOTON_RECURSE_DEPTH,
+    MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+};
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::materials::microfacet_material::MicrofacetMaterial;
+use crate::microfacet::{
+    direct_light, ggx_specular, glossy_sample_count, roughness_to_alpha, sample_ggx_reflection,
+    schlick_fresnel,
+};
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+
+// Wraps another material under a clear coat, like varnish, lacquer or car paint
+// The coat reflects some light, given by its refractive index and the Fresnel
+//  equations, so less reaches the base below, especially at grazing angles
+// The coat can absorb light on its way to the base and back out, tinting the base
+//  more the thicker the coat is
+// A roughness above 0 blurs the reflections in the coat
+#[derive(Debug)]
+pub struct LayeredMaterial {
+    base: Box<dyn Material + Sync + Send>,
+    refractive_index: f64,
+    roughness: f64,
+    // Fraction of each color absorbed per unit distance in the coat, black absorbs nothing
+    absorption: Color,
+    thickness: f64,
+}
+
+impl LayeredMaterial {
+    pub fn new(
+        base: Box<dyn Material + Sync + Send>,
+        refractive_index: f64,
+        roughness: f64,
+    ) -> Self {
+        assert!((0. ..=1.).contains(&roughness));
+        LayeredMaterial {
+            base,
+            refractive_index,
+            roughness,
+            absorption: Color::new_black(),
+            thickness: 0.,
+        }
+    }
+
+    // Helper constructor for a metallic paint under a glossy clear coat
+    pub fn new_car_paint(color: Color) -> Self {
+        LayeredMaterial::new(Box::new(MicrofacetMaterial::new(color, 0.4, 0.6)), 1.5, 0.)
+    }
+
+    // Makes the coat absorb light following the Beer-Lambert law, over a thickness
+    //  in the same units as the scene
+    pub fn with_coat_absorption(mut self, absorption: Color, thickness: f64) -> Self {
+        self.absorption = absorption;
+        self.thickness = thickness;
+        self
+    }
+
+    // Reflectance of the coat looking straight on
+    fn f0(&self) -> Color {
+        Color::new_grey(((self.refractive_index - 1.) / (self.refractive_index + 1.)).powi(2))
+    }
+
+    // Fraction of each color left after passing down through the coat and back out,
+    //  seen at an angle with a cosine to the normal
+    fn coat_tint(&self, cos_view: f64) -> Color {
+        // Light inside the coat is bent towards the normal, so travels less far than
+        //  the view ray would suggest
+        let sin_2_t_t = (1. - cos_view.clamp(0., 1.).powi(2)) / self.refractive_index.powi(2);
+        let distance = 2. * self.thickness / (1. - sin_2_t_t).sqrt().max(EPSILON);
+
+        Color::new(
+            (-self.absorption.red() * distance).exp(),
+            (-self.absorption.green() * distance).exp(),
+            (-self.absorption.blue() * distance).exp(),
+        )
+    }
+
+    // Fraction of each color that gets through the coat to the base and back out
+    fn coat_transmittance(&self, cos_view: f64) -> Color {
+        let fresnel = schlick_fresnel(self.f0(), cos_view);
+        (Color::new_grey(1.) + fresnel * -1.).piecewise_mul(&self.coat_tint(cos_view))
+    }
+
+    // Highlights from the lights in the scene reflected by the coat
+    fn highlights(&self, view: DVec3, hit: &Hit, scene: &Scene) -> Color {
+        let alpha = roughness_to_alpha(self.roughness);
+        direct_light(hit, scene, |light| {
+            ggx_specular(*hit.normal(), view, light, alpha, |cos_theta| {
+                schlick_fresnel(self.f0(), cos_theta)
+            })
+        })
+    }
+
+    // Picks a direction reflected by the coat and its weight
+    fn sample_reflection(&self, view: DVec3, normal: DVec3) -> Option<(DVec3, Color)> {
+        sample_ggx_reflection(
+            view,
+            normal,
+            roughness_to_alpha(self.roughness),
+            |cos_theta| schlick_fresnel(self.f0(), cos_theta),
+        )
+    }
+}
+
+impl Material for LayeredMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        ambient: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let view = -view_ray.direction().normalize();
+
+        // The base is only lit by the light getting through the coat
+        let transmittance = self.coat_transmittance(hit.normal().dot(view));
+        let base = self
+            .base
+            .compute(
+                view_ray,
+                hit,
+                ambient,
+                scene,
+                recurse_depth,
+                recurse_power.piecewise_mul(&transmittance),
+            )
+            .piecewise_mul(&transmittance);
+
+        let highlights = self.highlights(view, hit, scene);
+
+        if recurse_depth >= MAX_RECURSE_DEPTH || recurse_power.min_val() <= MIN_RECURSE_COEFFICIENT
+        {
+            return base + highlights;
+        }
+
+        // Smooth coats only need the one perfect reflection
+        let samples = if self.roughness > 0. {
+            glossy_sample_count(DEFAULT_GLOSSY_SAMPLES, recurse_depth)
+        } else {
+            1
+        };
+
+        let reflection = (0..samples)
+            .filter_map(|_| self.sample_reflection(view, *hit.normal()))
+            .map(|(dir, reflectance)| {
+                scene
+                    .calc_ray(
+                        Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                        recurse_power.piecewise_mul(&reflectance),
+                        recurse_depth + 1,
+                    )
+                    .0
+                    .piecewise_mul(&reflectance)
+            })
+            .sum::<Color>()
+            * (1. / samples as f64);
+
+        base + highlights + reflection
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        let view = -view_ray.direction().normalize();
+        let cos_view = hit.normal().dot(view).abs();
+
+        // Randomly either reflects off the coat or passes through it to the base
+        let mut rng = rand::thread_rng();
+        if rng.gen_range(0. ..1.) < schlick_fresnel(self.f0(), cos_view).magnitude() {
+            let Some((dir, _)) = self.sample_reflection(view, *hit.normal()) else {
+                return vec![];
+            };
+
+            return scene.calculate_photon_ray(
+                Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                light_index,
+                recurse_depth,
+                recurse_power,
+            );
+        }
+
+        // The photon survives the coat with the chance of not being absorbed, so its
+        //  power only changes color
+        let tint = self.coat_tint(cos_view);
+        if tint.magnitude() <= 0. || rng.gen_range(0. ..1.) >= tint.magnitude() {
+            return vec![];
+        }
+
+        self.base.compute_photon(
+            view_ray,
+            hit,
+            scene,
+            recurse_depth,
+            recurse_power.mul_const_mag(&tint),
+            light_index,
+        )
+    }
+
+    // A smooth enough coat focuses light into caustics, as well as the base
+    fn needs_caustic(&self) -> bool {
+        self.roughness <= MAX_CAUSTIC_ROUGHNESS || self.base.needs_caustic()
+    }
+
+    fn shadow_transmittance(&self, hit: &Hit) -> Color {
+        self.base
+            .shadow_transmittance(hit)
+            .piecewise_mul(&self.coat_transmittance(1.))
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.base.is_transparent()
+    }
+
+    fn is_two_sided(&self) -> bool {
+        self.base.is_two_sided()
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        light_index: usize,
+        color: Color,
+    ) -> Option<Photon> {
+        // If exceeded recurse depth, skip
+        if recurse_depth > MAX_PHOTON_RECURSE_DEPTH {
+            return None;
+        }
+
+        let view = -view_ray.direction().normalize();
+        let cos_view = hit.normal().dot(view).abs();
+
+        // Randomly either reflects off the coat or passes through it to the base, the
+        //  same as photons
+        let mut rng = rand::thread_rng();
+        if rng.gen_range(0. ..1.) < schlick_fresnel(self.f0(), cos_view).magnitude() {
+            if self.roughness > MAX_CAUSTIC_ROUGHNESS {
+                return None;
+            }
+
+            let (dir, _) = self.sample_reflection(view, *hit.normal())?;
+            return scene.calculate_caustic(
+                &Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                hit.get_object_index(),
+                light_index,
+                color,
+                recurse_depth,
+            );
+        }
+
+        self.base.compute_caustic_ray(
+            view_ray,
+            hit,
+            scene,
+            recurse_depth,
+            light_index,
+            color.piecewise_mul(&self.coat_tint(cos_view)),
+        )
+    }
+}
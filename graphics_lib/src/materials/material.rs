@@ -3,6 +3,7 @@ use crate::hit::Hit;
 use crate::photon::Photon;
 use crate::ray::Ray;
 use crate::scene::Scene;
+use crate::volumes::volume::Volume;
 use std::fmt::Debug;
 
 // Trait for materials to adhere to
@@ -29,8 +30,34 @@ pub trait Material: Debug {
     ) -> Vec<Photon>;
 
     // Specifies if this material needs caustic photons
+    // They are only made for objects with finite caustic bounds
     fn needs_caustic(&self) -> bool;
 
+    // How much light passes straight through a surface of this material to a shadow ray
+    // Most materials are opaque and block all of it
+    fn shadow_transmittance(&self, _hit: &Hit) -> Color {
+        Color::new_black()
+    }
+
+    // Whether light passes into objects with this material, so rays can travel
+    //  inside them
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    // Whether the back of a surface with this material can be seen
+    // Most materials are only seen from outside of closed objects, open surfaces like
+    //  windows need both sides
+    fn is_two_sided(&self) -> bool {
+        false
+    }
+
+    // The volume filling objects with this material, which rays inside the object
+    //  scatter in
+    fn volume(&self) -> Option<&(dyn Volume + Sync + Send)> {
+        None
+    }
+
     // Finds the outgoing ray of a caustic photon hitting this object
     fn compute_caustic_ray(
         &self,
@@ -0,0 +1,383 @@
+use crate::color::Color;
+use crate::materials::compound_material::CompoundMaterial;
+use crate::materials::conductor_material::{ConductorMaterial, Metal};
+use crate::materials::emissive_material::EmissiveMaterial;
+use crate::materials::false_color_material::FalseColorMaterial;
+use crate::materials::layered_material::LayeredMaterial;
+use crate::materials::material::Material;
+use crate::materials::microfacet_material::MicrofacetMaterial;
+use crate::materials::refractive_index::RefractiveIndex;
+use crate::materials::subsurface_material::SubsurfaceMaterial;
+use crate::materials::thin_sheet_material::ThinSheetMaterial;
+use std::f64::consts::FRAC_PI_2;
+use std::io::BufRead;
+
+// A list of materials with names, so scenes can refer to materials by name rather
+//  than by their position in the list
+// The materials are handed to the scene in the same order, so the index of a name
+//  is the material index objects use
+#[derive(Debug, Default)]
+pub struct MaterialLibrary {
+    names: Vec<String>,
+    materials: Vec<Box<dyn Material + Sync + Send>>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        MaterialLibrary::default()
+    }
+
+    // Loads materials from a file, one per line, as a name, a kind of material and
+    //  its parameters separated by spaces, eg.
+    //      glass transparent 1.5 0
+    //      red_paint matte 0.8 0.1 0.1 0.2
+    // Colors are three numbers for red, green and blue
+    // Blank lines and lines starting with # are ignored
+    // Note this can fail!
+    pub fn from_file(file: impl BufRead) -> Result<Self, String> {
+        let mut library = MaterialLibrary::new();
+
+        for (i, line) in file.lines().enumerate() {
+            let line = line.map_err(|_| "Failed to read line.")?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            let material = parse_material(&words[1..])
+                .map_err(|e| format!("Line {} malformed ({}).", i + 1, e))?;
+            library
+                .add(words[0], material)
+                .map_err(|e| format!("Line {} malformed ({}).", i + 1, e))?;
+        }
+
+        Ok(library)
+    }
+
+    // Adds a material to the end of the library, returning its index
+    pub fn add(
+        &mut self,
+        name: &str,
+        material: Box<dyn Material + Sync + Send>,
+    ) -> Result<usize, String> {
+        if self.names.iter().any(|n| n == name) {
+            return Err(format!("Material {} already exists.", name));
+        }
+
+        self.names.push(name.to_string());
+        self.materials.push(material);
+        Ok(self.materials.len() - 1)
+    }
+
+    // Finds the index of a material by its name
+    pub fn index(&self, name: &str) -> Result<usize, String> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .ok_or(format!("Unknown material {}.", name))
+    }
+
+    pub fn names(&self) -> &Vec<String> {
+        &self.names
+    }
+
+    // Gives the materials to pass to the scene
+    pub fn into_materials(self) -> Vec<Box<dyn Material + Sync + Send>> {
+        self.materials
+    }
+}
+
+// Makes a material from its kind and parameters
+fn parse_material(words: &[&str]) -> Result<Box<dyn Material + Sync + Send>, String> {
+    let (kind, params) = words.split_first().ok_or("no material kind")?;
+    let params = Params(params);
+
+    let material: Box<dyn Material + Sync + Send> = match *kind {
+        "matte" => {
+            params.expect(4)?;
+            Box::new(CompoundMaterial::new_matte_material(
+                params.color(0)?,
+                params.fraction(3)?,
+            ))
+        }
+        "rough_matte" => {
+            params.expect(4)?;
+            Box::new(CompoundMaterial::new_rough_matte_material(
+                params.color(0)?,
+                params.angle(3)?,
+            ))
+        }
+        "reflective" => {
+            params.expect(5)?;
+            Box::new(CompoundMaterial::new_rough_reflective_material(
+                params.color(0)?,
+                params.fraction(3)?,
+                params.fraction(4)?,
+            ))
+        }
+        "fresnel_reflective" => {
+            params.expect(5)?;
+            Box::new(CompoundMaterial::new_fresnel_reflective_material(
+                params.color(0)?,
+                params.refractive_index(3)?,
+                params.fraction(4)?,
+            ))
+        }
+        "transparent" => {
+            params.expect(2)?;
+            Box::new(CompoundMaterial::new_frosted_transparent_material(
+                params.refractive_index(0)?,
+                params.fraction(1)?,
+            ))
+        }
+        "absorbing" => {
+            params.expect(5)?;
+            Box::new(CompoundMaterial::new_absorbing_transparent_material(
+                params.refractive_index(0)?,
+                params.fraction(1)?,
+                params.color(2)?,
+            ))
+        }
+        "transparent_opacity" => {
+            params.expect(5)?;
+            Box::new(CompoundMaterial::new_transparent_material_opacity(
+                params.refractive_index(0)?,
+                params.color(1)?,
+                params.fraction(4)?,
+            ))
+        }
+        "dispersive" => {
+            params.expect(1)?;
+            let refractive_index = match params.0[0] {
+                "bk7" => RefractiveIndex::bk7(),
+                "dense_flint" => RefractiveIndex::dense_flint(),
+                "diamond" => RefractiveIndex::diamond(),
+                "water" => RefractiveIndex::water(),
+                other => return Err(format!("unknown glass {}", other)),
+            };
+            Box::new(CompoundMaterial::new_dispersive_transparent_material(
+                refractive_index,
+            ))
+        }
+        "metal" => {
+            params.expect(2)?;
+            let metal = match params.0[0] {
+                "gold" => Metal::Gold,
+                "silver" => Metal::Silver,
+                "copper" => Metal::Copper,
+                "aluminium" => Metal::Aluminium,
+                "iron" => Metal::Iron,
+                other => return Err(format!("unknown metal {}", other)),
+            };
+            Box::new(ConductorMaterial::from_metal(metal, params.fraction(1)?))
+        }
+        "microfacet" => {
+            params.expect(5)?;
+            Box::new(MicrofacetMaterial::new(
+                params.color(0)?,
+                params.fraction(3)?,
+                params.fraction(4)?,
+            ))
+        }
+        "car_paint" => {
+            params.expect(3)?;
+            Box::new(LayeredMaterial::new_car_paint(params.color(0)?))
+        }
+        "thin_sheet" => {
+            params.expect(4)?;
+            Box::new(
+                ThinSheetMaterial::new(params.refractive_index(0)?).with_tint(params.color(1)?),
+            )
+        }
+        "subsurface" => {
+            params.expect(1)?;
+            Box::new(match params.0[0] {
+                "wax" => SubsurfaceMaterial::new_wax(),
+                "marble" => SubsurfaceMaterial::new_marble(),
+                "skin" => SubsurfaceMaterial::new_skin(),
+                other => return Err(format!("unknown subsurface material {}", other)),
+            })
+        }
+        "emissive" => {
+            params.expect(3)?;
+            Box::new(EmissiveMaterial::new(params.color(0)?))
+        }
+        "false_color" => {
+            params.expect(1)?;
+            Box::new(FalseColorMaterial::new(params.number(0)?))
+        }
+        other => return Err(format!("unknown material kind {}", other)),
+    };
+
+    Ok(material)
+}
+
+// The parameters after the kind of a material
+struct Params<'a>(&'a [&'a str]);
+
+impl Params<'_> {
+    fn expect(&self, count: usize) -> Result<(), String> {
+        if self.0.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} parameters, found {}",
+                count,
+                self.0.len()
+            ))
+        }
+    }
+
+    fn number(&self, i: usize) -> Result<f64, String> {
+        self.0[i]
+            .parse::<f64>()
+            .map_err(|_| format!("{} is not a number", self.0[i]))
+    }
+
+    // An angle in radians between 0 and a right angle
+    fn angle(&self, i: usize) -> Result<f64, String> {
+        let n = self.number(i)?;
+        if (0. ..=FRAC_PI_2).contains(&n) {
+            Ok(n)
+        } else {
+            Err(format!("{} is not an angle between 0 and pi/2", self.0[i]))
+        }
+    }
+
+    // A number between 0 and 1
+    fn fraction(&self, i: usize) -> Result<f64, String> {
+        let n = self.number(i)?;
+        if (0. ..=1.).contains(&n) {
+            Ok(n)
+        } else {
+            Err(format!("{} is not between 0 and 1", self.0[i]))
+        }
+    }
+
+    // A refractive index, which cannot be below 1
+    fn refractive_index(&self, i: usize) -> Result<f64, String> {
+        let n = self.number(i)?;
+        if n >= 1. {
+            Ok(n)
+        } else {
+            Err(format!("{} is less than 1", self.0[i]))
+        }
+    }
+
+    fn color(&self, i: usize) -> Result<Color, String> {
+        Ok(Color::new(
+            self.number(i)?,
+            self.number(i + 1)?,
+            self.number(i + 2)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn library(text: &str) -> Result<MaterialLibrary, String> {
+        MaterialLibrary::from_file(Cursor::new(text))
+    }
+
+    fn error(text: &str) -> String {
+        library(text).unwrap_err()
+    }
+
+    #[test]
+    fn parses_materials_in_order() {
+        let library = library(
+            "glass transparent 1.5 0\n\
+             red_paint matte 0.8 0.1 0.1 0.2\n\
+             gold metal gold 0.3\n\
+             lamp emissive 4 4 4\n",
+        )
+        .unwrap();
+
+        assert_eq!(library.names(), &vec!["glass", "red_paint", "gold", "lamp"]);
+        assert_eq!(library.index("red_paint"), Ok(1));
+        assert_eq!(library.index("lamp"), Ok(3));
+        assert_eq!(library.into_materials().len(), 4);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let library = library(
+            "# a comment\n\
+             \n\
+             \x20  \n\
+             \x20 # an indented comment\n\
+             \x20 wax subsurface wax  \n",
+        )
+        .unwrap();
+
+        assert_eq!(library.names(), &vec!["wax"]);
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        assert_eq!(
+            error("# header\nfoo sparkly 1\n"),
+            "Line 2 malformed (unknown material kind sparkly)."
+        );
+        assert_eq!(
+            error("foo matte 0.5 0.5 0.5\n"),
+            "Line 1 malformed (expected 4 parameters, found 3)."
+        );
+        assert_eq!(
+            error("foo matte 0.5 x 0.5 0.5\n"),
+            "Line 1 malformed (x is not a number)."
+        );
+        assert_eq!(
+            error("foo matte 0.5 0.5 0.5 2\n"),
+            "Line 1 malformed (2 is not between 0 and 1)."
+        );
+        assert_eq!(
+            error("foo rough_matte 0.5 0.5 0.5 -0.1\n"),
+            "Line 1 malformed (-0.1 is not an angle between 0 and pi/2)."
+        );
+        assert_eq!(
+            error("foo metal lead 0.1\n"),
+            "Line 1 malformed (unknown metal lead)."
+        );
+        assert_eq!(error("foo\n"), "Line 1 malformed (no material kind).");
+    }
+
+    #[test]
+    fn refractive_index_below_one() {
+        assert_eq!(
+            error("glass transparent 0.5 0\n"),
+            "Line 1 malformed (0.5 is less than 1)."
+        );
+        assert_eq!(
+            error("film thin_sheet 0.9 1 1 1\n"),
+            "Line 1 malformed (0.9 is less than 1)."
+        );
+        assert_eq!(
+            error("plastic fresnel_reflective 0.1 0.1 0.1 0.5 0\n"),
+            "Line 1 malformed (0.5 is less than 1)."
+        );
+        assert!(library("vacuum transparent 1 0\n").is_ok());
+    }
+
+    #[test]
+    fn duplicate_names() {
+        assert_eq!(
+            error("a matte 1 1 1 0\nb matte 1 1 1 0\na matte 1 1 1 0\n"),
+            "Line 3 malformed (Material a already exists.)."
+        );
+    }
+
+    #[test]
+    fn unknown_name() {
+        let mut library = MaterialLibrary::new();
+        assert_eq!(
+            library.add("a", Box::new(EmissiveMaterial::new(Color::new(1., 1., 1.)))),
+            Ok(0)
+        );
+        assert_eq!(library.index("b"), Err("Unknown material b.".to_string()));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::color::Color;
+use crate::constants::{
+    DEFAULT_AMBIENT, DEFAULT_GLOSSY_SAMPLES, EPSILON, MAX_CAUSTIC_ROUGHNESS,
+    MAX_PHOTON_RECURSE_DEPTH, MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+};
+use crate::fibonacci_spiral::cosine_hemisphere_random;
+use crate::hit::Hit;
+use crate::materials::ambient_material::AmbientMaterial;
+use crate::materials::material::Material;
+use crate::microfacet::{
+    direct_light, ggx_specular, glossy_sample_count, reflect, roughness_to_alpha,
+    sample_ggx_normal, sample_ggx_reflection, schlick_fresnel,
+};
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+
+// Reflectance of non metals looking straight on
+const DIELECTRIC_REFLECTANCE: f64 = 0.04;
+
+// This material is a physically based rough surface, using the Cook-Torrance model
+//      with a GGX distribution, Smith shadowing and Schlick's Fresnel
+// Roughness goes from 0 (polished) to 1 (completely rough)
+// Metals reflect tinted by the base color with no diffuse part, non metals have a
+//  diffuse base color under a white reflection
+// Light is scaled to match DiffuseMaterial, so a rough white non metal is about as
+//  bright as a matte white material
+#[derive(Debug, Clone)]
+pub struct MicrofacetMaterial {
+    base_color: Color,
+    roughness: f64,
+    metallic: f64,
+    // Number of rays used to find reflections of the rest of the scene
+    glossy_samples: usize,
+}
+
+impl MicrofacetMaterial {
+    pub fn new(base_color: Color, roughness: f64, metallic: f64) -> Self {
+        assert!((0. ..=1.).contains(&roughness));
+        assert!((0. ..=1.).contains(&metallic));
+        MicrofacetMaterial {
+            base_color,
+            roughness,
+            metallic,
+            glossy_samples: DEFAULT_GLOSSY_SAMPLES,
+        }
+    }
+
+    // Sets the number of reflection rays, more is smoother but slower
+    // The number halves at each recursion so reflections of reflections stay cheap
+    pub fn with_glossy_samples(mut self, glossy_samples: usize) -> Self {
+        self.glossy_samples = glossy_samples.max(1);
+        self
+    }
+
+    // Reflectance looking straight on
+    fn f0(&self) -> Color {
+        Color::new_grey(DIELECTRIC_REFLECTANCE) * (1. - self.metallic)
+            + self.base_color * self.metallic
+    }
+
+    // Light reflected towards the viewer from light arriving along a direction
+    // Includes the cosine of the light, and is multiplied by PI to match DiffuseMaterial
+    fn brdf_cos(&self, hit: &Hit, view: DVec3, light: DVec3) -> Color {
+        let normal = *hit.normal();
+        let cos_light = normal.dot(light);
+        if normal.dot(view) <= 0. || cos_light <= 0. {
+            return Color::new_black();
+        }
+
+        let fresnel = |cos_theta| schlick_fresnel(self.f0(), cos_theta);
+        let specular = ggx_specular(
+            normal,
+            view,
+            light,
+            roughness_to_alpha(self.roughness),
+            fresnel,
+        );
+
+        // Light not reflected by the surface enters it and is scattered diffusely
+        let diffuse = (Color::new_grey(1.) + fresnel(view.dot((view + light).normalize())) * -1.)
+            .piecewise_mul(&self.base_color)
+            * ((1. - self.metallic) * cos_light);
+
+        diffuse + specular
+    }
+
+    // Reflections of the rest of the scene, averaged over rays spread by the roughness
+    fn glossy_reflection(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let normal = *hit.normal();
+        let view = -view_ray.direction().normalize();
+        if normal.dot(view) <= 0. {
+            return Color::new_black();
+        }
+
+        let alpha = roughness_to_alpha(self.roughness);
+        let samples = glossy_sample_count(self.glossy_samples, recurse_depth);
+
+        (0..samples)
+            .map(|_| {
+                let Some((dir, reflectance)) =
+                    sample_ggx_reflection(view, normal, alpha, |cos_theta| {
+                        schlick_fresnel(self.f0(), cos_theta)
+                    })
+                else {
+                    return Color::new_black();
+                };
+
+                let (col, _) = scene.calc_ray(
+                    Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                    recurse_power.piecewise_mul(&reflectance),
+                    recurse_depth + 1,
+                );
+                col.piecewise_mul(&reflectance)
+            })
+            .sum::<Color>()
+            * (1. / samples as f64)
+    }
+}
+
+impl Material for MicrofacetMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        ambient: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let view = -view_ray.direction().normalize();
+
+        // Direct light from each light in the scene
+        let direct = direct_light(hit, scene, |light| self.brdf_cos(hit, view, light));
+
+        // Indirect light from the photon map onto the diffuse part
+        let indirect = AmbientMaterial::new()
+            .compute(view_ray, hit, ambient, scene, recurse_depth, recurse_power)
+            .piecewise_mul(&self.base_color)
+            * (DEFAULT_AMBIENT * (1. - self.metallic));
+
+        let reflection = if recurse_depth < MAX_RECURSE_DEPTH
+            && recurse_power.min_val() > MIN_RECURSE_COEFFICIENT
+        {
+            self.glossy_reflection(view_ray, hit, scene, recurse_depth, recurse_power)
+        } else {
+            Color::new_black()
+        };
+
+        direct + indirect + reflection
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        let normal = *hit.normal();
+        let view = -view_ray.direction().normalize();
+        let alpha = roughness_to_alpha(self.roughness);
+
+        // Randomly either reflects off the facets or scatters diffusely, with the
+        //  chance of reflecting set by the Fresnel reflectance
+        let reflect_chance = schlick_fresnel(self.f0(), normal.dot(view).abs()).magnitude();
+        let reflect_chance = reflect_chance + (1. - reflect_chance) * self.metallic;
+
+        let mut rng = rand::thread_rng();
+        let (dir, tint) = if rng.gen_range(0. ..1.) < reflect_chance {
+            let facet = sample_ggx_normal(normal, alpha);
+            (
+                reflect(-view, facet),
+                schlick_fresnel(self.f0(), view.dot(facet).abs()),
+            )
+        } else {
+            (cosine_hemisphere_random(normal), self.base_color)
+        };
+
+        // Photons scattered into the surface or by black surfaces are absorbed
+        let mut res = if normal.dot(dir) > 0. && tint.magnitude() > 0. {
+            scene.calculate_photon_ray(
+                Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                light_index,
+                recurse_depth,
+                recurse_power.mul_const_mag(&tint),
+            )
+        } else {
+            vec![]
+        };
+
+        // Also add an indirect photon at this point
+        res.push(Photon::new_indirect(
+            *hit.pos(),
+            light_index,
+            recurse_power,
+            hit.get_object_index(),
+        ));
+
+        res
+    }
+
+    // Polished surfaces focus light into caustics, only through their specular
+    //  reflection
+    fn needs_caustic(&self) -> bool {
+        self.roughness <= MAX_CAUSTIC_ROUGHNESS
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        light_index: usize,
+        color: Color,
+    ) -> Option<Photon> {
+        // If exceeded recurse depth, skip
+        if recurse_depth > MAX_PHOTON_RECURSE_DEPTH {
+            return None;
+        }
+
+        let normal = *hit.normal();
+        let view = -view_ray.direction().normalize();
+        let alpha = roughness_to_alpha(self.roughness);
+        let (dir, reflectance) = sample_ggx_reflection(view, normal, alpha, |cos_theta| {
+            schlick_fresnel(self.f0(), cos_theta)
+        })?;
+
+        scene.calculate_caustic(
+            &Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+            hit.get_object_index(),
+            light_index,
+            color.piecewise_mul(&reflectance),
+            recurse_depth,
+        )
+    }
+}
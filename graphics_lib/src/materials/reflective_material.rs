@@ -1,19 +1,43 @@
 use crate::color::Color;
-use crate::constants::{EPSILON, MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT};
+use crate::constants::{
+    DEFAULT_GLOSSY_SAMPLES, EPSILON, MAX_CAUSTIC_ROUGHNESS, MAX_PHOTON_RECURSE_DEPTH,
+    MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+};
 use crate::hit::Hit;
 use crate::materials::material::Material;
+use crate::microfacet::{glossy_sample_count, reflect, sample_facet};
 use crate::photon::Photon;
 use crate::ray::Ray;
 use crate::scene::Scene;
 use glam::DVec3;
 
-// This material is a perfectly reflective material
+// This material is a reflective material
+// A roughness above 0 blurs the reflection, like satin or brushed metal, by averaging
+//  rays reflected off randomly tilted facets
 #[derive(Clone, Debug)]
-pub struct ReflectiveMaterial {}
+pub struct ReflectiveMaterial {
+    roughness: f64,
+}
 
 impl ReflectiveMaterial {
-    pub(crate) fn new() -> ReflectiveMaterial {
-        ReflectiveMaterial {}
+    // A roughness of 0 is a perfect mirror
+    pub(crate) fn new(roughness: f64) -> ReflectiveMaterial {
+        assert!((0. ..=1.).contains(&roughness));
+        ReflectiveMaterial { roughness }
+    }
+
+    // Direction of one reflected ray, perturbed by the roughness
+    fn reflection_dir(&self, view_ray: Ray, hit: &Hit) -> DVec3 {
+        let incidence = view_ray.direction().normalize();
+        let facet = sample_facet(*hit.normal(), incidence, self.roughness);
+        let reflection_dir = reflect(incidence, facet);
+
+        // Facets can reflect rays into the surface, these use the perfect reflection
+        if reflection_dir.dot(*hit.normal()) * incidence.dot(*hit.normal()) < 0. {
+            reflection_dir
+        } else {
+            reflect(incidence, *hit.normal())
+        }
     }
 }
 
@@ -28,12 +52,25 @@ impl Material for ReflectiveMaterial {
         recurse_power: Color,
     ) -> Color {
         if recurse_depth < MAX_RECURSE_DEPTH && recurse_power.min_val() > MIN_RECURSE_COEFFICIENT {
-            let reflection_dir: DVec3 = view_ray.direction()
-                - 2. * (view_ray.direction().dot(*hit.normal())) * *hit.normal();
-            let reflection_dir = reflection_dir.normalize();
+            // Smooth surfaces only need the one perfect reflection
+            let samples = if self.roughness > 0. {
+                glossy_sample_count(DEFAULT_GLOSSY_SAMPLES, recurse_depth)
+            } else {
+                1
+            };
 
-            let reflection_ray = Ray::new(*hit.pos() + reflection_dir * EPSILON, reflection_dir);
-            let (col, _) = scene.calc_ray(reflection_ray, recurse_power, recurse_depth + 1);
+            let col = (0..samples)
+                .map(|_| {
+                    let reflection_dir = self.reflection_dir(view_ray, hit);
+                    let reflection_ray =
+                        Ray::new(*hit.pos() + reflection_dir * EPSILON, reflection_dir)
+                            .with_media(view_ray.media());
+                    scene
+                        .calc_ray(reflection_ray, recurse_power, recurse_depth + 1)
+                        .0
+                })
+                .sum::<Color>()
+                * (1. / samples as f64);
 
             col.piecewise_mul(&recurse_power)
         } else {
@@ -50,32 +87,43 @@ impl Material for ReflectiveMaterial {
         recurse_power: Color,
         light_index: usize,
     ) -> Vec<Photon> {
-        let reflection_dir: DVec3 =
-            view_ray.direction() - 2. * (view_ray.direction().dot(*hit.normal())) * *hit.normal();
-        let reflection_dir = reflection_dir.normalize();
+        let reflection_dir = self.reflection_dir(view_ray, hit);
         scene.calculate_photon_ray(
-            Ray::new(*hit.pos(), reflection_dir),
+            Ray::new(*hit.pos(), reflection_dir).with_media(view_ray.media()),
             light_index,
             recurse_depth,
             recurse_power,
         )
     }
 
+    // Smooth enough mirrors focus light into caustics, eg. the bright curve inside a
+    //  ring or under a curved mirror
     fn needs_caustic(&self) -> bool {
-        false
+        self.roughness <= MAX_CAUSTIC_ROUGHNESS
     }
 
     fn compute_caustic_ray(
         &self,
-        _view_ray: Ray,
-        _hit: &Hit,
-        _scene: &Scene,
-        _recurse_depth: usize,
-        _light_index: usize,
-        _: Color,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        light_index: usize,
+        color: Color,
     ) -> Option<Photon> {
-        // Does not retransmit caustics
-        // TODO: Could change this?
-        None
+        // If exceeded recurse depth, skip
+        if recurse_depth > MAX_PHOTON_RECURSE_DEPTH {
+            return None;
+        }
+
+        let reflection_dir = self.reflection_dir(view_ray, hit);
+        scene.calculate_caustic(
+            &Ray::new(*hit.pos() + reflection_dir * EPSILON, reflection_dir)
+                .with_media(view_ray.media()),
+            hit.get_object_index(),
+            light_index,
+            color,
+            recurse_depth,
+        )
     }
 }
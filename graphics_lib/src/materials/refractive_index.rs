@@ -0,0 +1,110 @@
+use crate::color::Color;
+use crate::constants::CHANNEL_WAVELENGTHS;
+
+// A refractive index that changes with the wavelength of light, splitting white
+//  light into colors (dispersion)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefractiveIndex {
+    // n = a + b / wavelength^2, with the wavelength in micrometres
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum of b * wavelength^2 / (wavelength^2 - c), with the wavelength
+    //  in micrometres
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl RefractiveIndex {
+    // Common crown glass, only a little dispersion
+    pub fn bk7() -> Self {
+        RefractiveIndex::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    // Dense flint glass, used for prisms and cut crystal as it has strong dispersion
+    pub fn dense_flint() -> Self {
+        RefractiveIndex::Sellmeier {
+            b: [1.34533359, 0.209073176, 0.937357162],
+            c: [0.00997743871, 0.0470450767, 111.886764],
+        }
+    }
+
+    pub fn diamond() -> Self {
+        RefractiveIndex::Cauchy { a: 2.38, b: 0.0135 }
+    }
+
+    pub fn water() -> Self {
+        RefractiveIndex::Cauchy {
+            a: 1.3199,
+            b: 0.00653,
+        }
+    }
+
+    // The refractive index at a wavelength in micrometres
+    pub fn at_wavelength(&self, wavelength: f64) -> f64 {
+        let wavelength2 = wavelength * wavelength;
+        match self {
+            RefractiveIndex::Cauchy { a, b } => a + b / wavelength2,
+            RefractiveIndex::Sellmeier { b, c } => (1.
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * wavelength2 / (wavelength2 - c))
+                    .sum::<f64>())
+            .sqrt(),
+        }
+    }
+
+    // The refractive index for one of the color channels
+    pub(crate) fn at_channel(&self, channel: usize) -> f64 {
+        self.at_wavelength(CHANNEL_WAVELENGTHS[channel])
+    }
+}
+
+// A color with only one channel set to 1
+pub(crate) fn channel_mask(channel: usize) -> Color {
+    match channel {
+        0 => Color::new(1., 0., 0.),
+        1 => Color::new(0., 1., 0.),
+        _ => Color::new(0., 0., 1.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wavelength of the sodium d line, where refractive indices are usually given
+    const D_LINE: f64 = 0.5876;
+
+    #[test]
+    fn sellmeier_matches_glass_catalogues() {
+        assert!((RefractiveIndex::bk7().at_wavelength(D_LINE) - 1.5168).abs() < 1E-4);
+        assert!((RefractiveIndex::dense_flint().at_wavelength(D_LINE) - 1.6200).abs() < 1E-4);
+    }
+
+    #[test]
+    fn cauchy() {
+        let index = RefractiveIndex::Cauchy { a: 1.5, b: 0.01 };
+        assert!((index.at_wavelength(0.5) - 1.54).abs() < 1E-12);
+        assert!((RefractiveIndex::diamond().at_wavelength(D_LINE) - 2.419).abs() < 1E-3);
+    }
+
+    #[test]
+    fn blue_bends_more_than_red() {
+        for index in [
+            RefractiveIndex::bk7(),
+            RefractiveIndex::dense_flint(),
+            RefractiveIndex::diamond(),
+            RefractiveIndex::water(),
+        ] {
+            assert!(index.at_channel(0) < index.at_channel(1));
+            assert!(index.at_channel(1) < index.at_channel(2));
+        }
+    }
+
+    #[test]
+    fn channel_masks() {
+        let total = (0..3).map(channel_mask).sum::<Color>();
+        assert_eq!((total.red(), total.green(), total.blue()), (1., 1., 1.));
+    }
+}
@@ -42,13 +42,11 @@ impl Material for SpecularMaterial {
             .get_lights()
             .iter()
             .enumerate()
-            .fold(Color::new_black(), |c, (i, light)| {
-                let intensity = light.get_intensity(*hit.pos(), scene, i);
-                let dir = light.get_direction(*hit.pos());
-
+            .flat_map(|(i, light)| light.get_samples(*hit.pos(), scene, i))
+            .fold(Color::new_black(), |c, (intensity, dir)| {
                 // Calculates the specular coefficient of this light
                 // Phong lighing model
-                let specular = reflection_dir.dot(-dir).powi(self.power as i32).max(0.);
+                let specular = reflection_dir.dot(-dir).powi(self.power).max(0.);
 
                 c + intensity * specular
             })
@@ -71,7 +69,7 @@ impl Material for SpecularMaterial {
         let reflection_dir = reflection_dir.normalize();
 
         scene.calculate_photon_ray(
-            Ray::new(*hit.pos(), reflection_dir),
+            Ray::new(*hit.pos(), reflection_dir).with_media(view_ray.media()),
             light_index,
             recurse_depth,
             recurse_power,
@@ -0,0 +1,323 @@
+use crate::color::Color;
+use crate::constants::{
+    DEFAULT_AMBIENT, DEFAULT_SUBSURFACE_SAMPLES, EPSILON, MAX_RECURSE_DEPTH, MAX_SUBSURFACE_EVENTS,
+    MIN_RECURSE_COEFFICIENT,
+};
+use crate::fibonacci_spiral::{cosine_hemisphere_random, fibonacci_spiral_random};
+use crate::hit::Hit;
+use crate::materials::ambient_material::AmbientMaterial;
+use crate::materials::diffuse_material::DiffuseMaterial;
+use crate::materials::material::Material;
+use crate::medium::{Medium, MediumStack};
+use crate::microfacet::{glossy_sample_count, reflect, schlick_fresnel};
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use rand::Rng;
+
+// This material scatters light under its surface, like wax, marble and skin
+// Light entering the surface takes a random walk inside the object, scattering and
+//  being absorbed, until it leaves somewhere else on the surface
+// The object must be closed, the walk finds where it leaves the same way
+//  TransparentMaterial finds internal hits
+// Colors that scatter further soften and bleed through thin parts more
+#[derive(Debug, Clone)]
+pub struct SubsurfaceMaterial {
+    refractive_index: f64,
+    // Chance per unit distance of each color scattering and being absorbed
+    scattering: Color,
+    absorption: Color,
+    // Number of random walks for each hit
+    samples: usize,
+}
+
+impl SubsurfaceMaterial {
+    pub fn new(refractive_index: f64, scattering: Color, absorption: Color) -> Self {
+        assert!(scattering.min_val() >= 0. && absorption.min_val() >= 0.);
+        assert!((scattering + absorption).min_val() > 0.);
+        SubsurfaceMaterial {
+            refractive_index,
+            scattering,
+            absorption,
+            samples: DEFAULT_SUBSURFACE_SAMPLES,
+        }
+    }
+
+    // Helper constructors for common materials, for objects about a unit across
+    pub fn new_wax() -> Self {
+        SubsurfaceMaterial::new(1.45, Color::new(12., 11., 9.), Color::new(0.05, 0.2, 0.6))
+    }
+
+    pub fn new_marble() -> Self {
+        SubsurfaceMaterial::new(1.5, Color::new(24., 25., 26.), Color::new(0.02, 0.04, 0.07))
+    }
+
+    pub fn new_skin() -> Self {
+        SubsurfaceMaterial::new(1.4, Color::new(8., 9., 10.), Color::new(0.3, 1.2, 2.))
+    }
+
+    // Sets the number of random walks, more is smoother but slower
+    // The number halves at each recursion so reflections of this material stay cheap
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    // Reflectance of the surface looking straight on
+    fn f0(&self) -> Color {
+        Color::new_grey(((self.refractive_index - 1.) / (self.refractive_index + 1.)).powi(2))
+    }
+
+    // Fraction of light reflected back inside when reaching the surface from inside,
+    //  given the cosine to the normal
+    fn internal_reflectance(&self, cos_inside: f64) -> f64 {
+        let sin_2_t_t = self.refractive_index.powi(2) * (1. - cos_inside.powi(2));
+        if sin_2_t_t >= 1. {
+            // Total internal reflection
+            return 1.;
+        }
+        schlick_fresnel(self.f0(), (1. - sin_2_t_t).sqrt()).magnitude()
+    }
+
+    // Follows light entering the surface at a hit until it leaves
+    // Returns where it leaves and how much of each color is left, or None if it is
+    //  absorbed or lost
+    // All the colors share one walk, each step goes a distance picked for one of the
+    //  colors, in proportion to how much of each is left, and every color is weighted
+    //  by how likely that distance was for it compared to all of them
+    fn random_walk(&self, hit: &Hit, media: MediumStack, scene: &Scene) -> Option<(Hit, Color)> {
+        let scattering = [
+            self.scattering.red(),
+            self.scattering.green(),
+            self.scattering.blue(),
+        ];
+        let extinction = [
+            scattering[0] + self.absorption.red(),
+            scattering[1] + self.absorption.green(),
+            scattering[2] + self.absorption.blue(),
+        ];
+
+        // The walk is inside this object, so finding its next surface finds where it
+        //  leaves the object
+        let object_index = hit.get_object_index();
+        let media = media.pushed(Medium::new(
+            object_index,
+            hit.get_csg_index(),
+            self.refractive_index,
+            0,
+        ));
+
+        let mut rng = rand::thread_rng();
+        let mut pos = *hit.pos();
+        // Light enters the surface diffusely
+        let mut dir = cosine_hemisphere_random(-*hit.normal());
+        let mut weight = [1.; 3];
+
+        for _ in 0..MAX_SUBSURFACE_EVENTS {
+            let total = weight.iter().sum::<f64>();
+            if total <= MIN_RECURSE_COEFFICIENT {
+                return None;
+            }
+
+            // Picks the color to find the distance for
+            let mut i: f64 = rng.gen_range(0. ..total);
+            let channel = (0..3)
+                .find(|c| {
+                    i -= weight[*c];
+                    i <= 0.
+                })
+                .unwrap_or(2);
+
+            let u: f64 = rng.gen_range(0. ..1.);
+            let distance = -(1. - u).ln() / extinction[channel];
+            let surface =
+                scene.next_hit(Ray::new(pos + dir * EPSILON, dir).with_media(media), 0.)?;
+
+            if surface.get_distance() > distance {
+                // Scatters inside the object in a new random direction, some of the
+                //  light is absorbed
+                let pdf = (0..3)
+                    .map(|c| weight[c] / total * extinction[c] * (-extinction[c] * distance).exp())
+                    .sum::<f64>();
+                for c in 0..3 {
+                    weight[c] *= scattering[c] * (-extinction[c] * distance).exp() / pdf;
+                }
+
+                pos += dir * (distance + EPSILON);
+                dir = fibonacci_spiral_random();
+                continue;
+            }
+
+            // Reaches a surface before scattering
+            let distance = surface.get_distance();
+            let pdf = (0..3)
+                .map(|c| weight[c] / total * (-extinction[c] * distance).exp())
+                .sum::<f64>();
+            for c in 0..3 {
+                weight[c] *= (-extinction[c] * distance).exp() / pdf;
+            }
+
+            if surface.get_object_index() != object_index || surface.get_dir() {
+                // Another object inside this one blocks the light
+                return None;
+            }
+
+            // Some light is reflected back inside by the surface
+            let cos_inside = surface.normal().dot(dir);
+            if rng.gen_range(0. ..1.) < self.internal_reflectance(cos_inside) {
+                pos = *surface.pos();
+                dir = reflect(dir, *surface.normal());
+                continue;
+            }
+
+            return Some((surface, Color::new(weight[0], weight[1], weight[2])));
+        }
+
+        None
+    }
+
+    // Light arriving at a point on the surface from outside, as a matte material would
+    //  receive it
+    fn light_at(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let white = Color::new_grey(1.);
+        DiffuseMaterial::new().compute(view_ray, hit, white, scene, recurse_depth, recurse_power)
+            * (1. - DEFAULT_AMBIENT)
+            + AmbientMaterial::new().compute(
+                view_ray,
+                hit,
+                white,
+                scene,
+                recurse_depth,
+                recurse_power,
+            ) * DEFAULT_AMBIENT
+    }
+}
+
+impl Material for SubsurfaceMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        _: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let incidence = view_ray.direction().normalize();
+        let fresnel = schlick_fresnel(self.f0(), -hit.normal().dot(incidence));
+
+        // Light reflected by the smooth surface
+        let refl_power = recurse_power.piecewise_mul(&fresnel);
+        let reflection = if refl_power.max_val() > MIN_RECURSE_COEFFICIENT
+            && recurse_depth < MAX_RECURSE_DEPTH
+        {
+            let dir = reflect(incidence, *hit.normal());
+            scene
+                .calc_ray(
+                    Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                    refl_power,
+                    recurse_depth + 1,
+                )
+                .0
+                .piecewise_mul(&fresnel)
+        } else {
+            Color::new_black()
+        };
+
+        // Light scattered under the surface, from where each walk leaves
+        let samples = glossy_sample_count(self.samples, recurse_depth);
+        let subsurface = (0..samples)
+            .filter_map(|_| {
+                let (exit, weight) = self.random_walk(hit, view_ray.media(), scene)?;
+                Some(
+                    self.light_at(view_ray, &exit, scene, recurse_depth, recurse_power)
+                        .piecewise_mul(&weight),
+                )
+            })
+            .sum::<Color>()
+            * (1. / samples as f64);
+
+        reflection + subsurface.piecewise_mul(&(Color::new_grey(1.) + fresnel * -1.))
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        let incidence = view_ray.direction().normalize();
+        let fresnel = schlick_fresnel(self.f0(), -hit.normal().dot(incidence)).magnitude();
+
+        let mut rng = rand::thread_rng();
+        if rng.gen_range(0. ..1.) < fresnel {
+            // Reflected by the surface
+            let dir = reflect(incidence, *hit.normal());
+            return scene.calculate_photon_ray(
+                Ray::new(*hit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+                light_index,
+                recurse_depth,
+                recurse_power,
+            );
+        }
+
+        // Also add an indirect photon at this point
+        let mut res = vec![Photon::new_indirect(
+            *hit.pos(),
+            light_index,
+            recurse_power,
+            hit.get_object_index(),
+        )];
+
+        // The photon walks under the surface, then leaves diffusely from where the
+        //  walk ends
+        let Some((exit, weight)) = self.random_walk(hit, view_ray.media(), scene) else {
+            return res;
+        };
+
+        let power = recurse_power.piecewise_mul(&weight);
+        res.push(Photon::new_indirect(
+            *exit.pos(),
+            light_index,
+            power,
+            exit.get_object_index(),
+        ));
+
+        let dir = cosine_hemisphere_random(*exit.normal());
+        res.append(&mut scene.calculate_photon_ray(
+            Ray::new(*exit.pos() + dir * EPSILON, dir).with_media(view_ray.media()),
+            light_index,
+            recurse_depth,
+            power,
+        ));
+
+        res
+    }
+
+    fn needs_caustic(&self) -> bool {
+        false
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        _view_ray: Ray,
+        _hit: &Hit,
+        _scene: &Scene,
+        _recurse_depth: usize,
+        _light_index: usize,
+        _: Color,
+    ) -> Option<Photon> {
+        // Does not retransmit caustics
+        None
+    }
+}
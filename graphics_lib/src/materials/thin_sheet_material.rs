@@ -0,0 +1,197 @@
+use crate::color::Color;
+use crate::constants::{EPSILON, MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT};
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::microfacet::reflect;
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use rand::Rng;
+
+// This material is a thin sheet of glass, like a window pane or a soap film
+// Unlike TransparentMaterial it does not need a closed object, the light reflects and
+//  passes through at a single surface so it works on planes and open meshes
+// Both sides of the surface can be seen, and the light passing through carries on in
+//  the same direction as the two faces of the sheet are parallel
+// Light bouncing back and forth between the two faces is included in the reflected
+//  and transmitted parts
+#[derive(Debug, Clone)]
+pub struct ThinSheetMaterial {
+    refractive_index: f64,
+    // Fraction of each color passing straight through the sheet once, white for clear
+    //  glass, light passing at an angle travels further so is tinted more
+    tint: Color,
+}
+
+impl ThinSheetMaterial {
+    pub fn new(refractive_index: f64) -> Self {
+        ThinSheetMaterial {
+            refractive_index,
+            tint: Color::new_grey(1.),
+        }
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    // The fractions of each color reflected and transmitted by the sheet, given the
+    //  cosine of the angle the light hits it at
+    fn coefficients(&self, cos_t_i: f64) -> (Color, Color) {
+        let cos_t_i = cos_t_i.clamp(0., 1.);
+        let sin_2_t_t = (1. - cos_t_i.powi(2)) / self.refractive_index.powi(2);
+        let cos_t_t = (1. - sin_2_t_t).sqrt();
+
+        // Fresnel equations for a single face, the light can never be totally
+        //  reflected going into the sheet
+        let n = self.refractive_index;
+        let r_s = ((cos_t_i - n * cos_t_t) / (cos_t_i + n * cos_t_t)).powi(2);
+        let r_p = ((n * cos_t_i - cos_t_t) / (n * cos_t_i + cos_t_t)).powi(2);
+        let r = ((r_s + r_p) / 2.).clamp(0., 1.);
+
+        // Sums the light bouncing between the faces, losing some to the tint each pass
+        let channel = |tint: f64| {
+            let t = tint.powf(1. / cos_t_t.max(EPSILON));
+            let denominator = 1. - r * r * t * t;
+            (
+                r + (1. - r).powi(2) * r * t * t / denominator,
+                (1. - r).powi(2) * t / denominator,
+            )
+        };
+
+        let (r_red, t_red) = channel(self.tint.red());
+        let (r_green, t_green) = channel(self.tint.green());
+        let (r_blue, t_blue) = channel(self.tint.blue());
+
+        (
+            Color::new(r_red, r_green, r_blue),
+            Color::new(t_red, t_green, t_blue),
+        )
+    }
+
+    // Finds the reflected and transmitted rays, with their coefficients
+    fn find_rays(&self, ray: Ray, hit: &Hit) -> (Ray, Color, Ray, Color) {
+        let incidence = ray.direction();
+
+        // The sheet can be hit from either side, so use the normal facing the ray
+        let normal = if hit.normal().dot(incidence) > 0. {
+            -*hit.normal()
+        } else {
+            *hit.normal()
+        };
+
+        let (refl_coeff, trans_coeff) = self.coefficients(-normal.dot(incidence));
+
+        let reflection_dir = reflect(incidence, normal);
+        let refl_ray =
+            Ray::new(*hit.pos() + reflection_dir * EPSILON, reflection_dir).with_media(ray.media());
+        let trans_ray =
+            Ray::new(*hit.pos() + incidence * EPSILON, incidence).with_media(ray.media());
+
+        (refl_ray, refl_coeff, trans_ray, trans_coeff)
+    }
+}
+
+impl Material for ThinSheetMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        _: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        let (refl_ray, refl_coeff, trans_ray, trans_coeff) = self.find_rays(view_ray, hit);
+
+        let refl_power = recurse_power.piecewise_mul(&refl_coeff);
+        let trans_power = recurse_power.piecewise_mul(&trans_coeff);
+
+        // Only calculate if coefficient big enough and not exceeded recurse depth
+        let refl_part = if refl_power.max_val() > MIN_RECURSE_COEFFICIENT
+            && recurse_depth < MAX_RECURSE_DEPTH
+        {
+            scene
+                .calc_ray(refl_ray, refl_power, recurse_depth + 1)
+                .0
+                .piecewise_mul(&refl_coeff)
+        } else {
+            Color::new_black()
+        };
+
+        // Only calculate if coefficient big enough and not exceeded recurse depth
+        let trans_part = if trans_power.max_val() > MIN_RECURSE_COEFFICIENT
+            && recurse_depth < MAX_RECURSE_DEPTH
+        {
+            scene
+                .calc_ray(trans_ray, trans_power, recurse_depth + 1)
+                .0
+                .piecewise_mul(&trans_coeff)
+        } else {
+            Color::new_black()
+        };
+
+        refl_part + trans_part
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        let (refl_ray, refl_coeff, trans_ray, trans_coeff) = self.find_rays(view_ray, hit);
+
+        // The photon is randomly reflected, transmitted or absorbed by the tint, so
+        //  its power only changes color
+        let mut rng = rand::thread_rng();
+        let i: f64 = rng.gen_range((0.)..1.);
+
+        let (ray, coeff) = if i < refl_coeff.magnitude() {
+            (refl_ray, refl_coeff)
+        } else if i < refl_coeff.magnitude() + trans_coeff.magnitude() {
+            (trans_ray, trans_coeff)
+        } else {
+            return vec![];
+        };
+
+        scene.calculate_photon_ray(
+            ray,
+            light_index,
+            recurse_depth,
+            recurse_power.mul_const_mag(&coeff),
+        )
+    }
+
+    // The light passing through is spread out by shadow rays rather than caustics,
+    //  as a flat sheet does not focus it
+    fn needs_caustic(&self) -> bool {
+        false
+    }
+
+    fn is_two_sided(&self) -> bool {
+        true
+    }
+
+    // Shadow rays pass through tinted by the sheet, taken as being hit straight on
+    fn shadow_transmittance(&self, _: &Hit) -> Color {
+        self.coefficients(1.).1
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        _view_ray: Ray,
+        _hit: &Hit,
+        _scene: &Scene,
+        _recurse_depth: usize,
+        _light_index: usize,
+        _: Color,
+    ) -> Option<Photon> {
+        // Does not retransmit caustics
+        None
+    }
+}
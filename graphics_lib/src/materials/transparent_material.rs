@@ -1,57 +1,154 @@
 use crate::color::Color;
 use crate::constants::{
-    EPSILON, MAX_PHOTON_RECURSE_DEPTH, MAX_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+    DEFAULT_GLOSSY_SAMPLES, EPSILON, MAX_PHOTON_RECURSE_DEPTH, MAX_RECURSE_DEPTH,
+    MIN_RECURSE_COEFFICIENT,
 };
 use crate::hit::Hit;
 use crate::materials::material::Material;
+use crate::materials::refractive_index::{channel_mask, RefractiveIndex};
+use crate::medium::{Medium, MediumStack};
+use crate::microfacet::{glossy_sample_count, sample_facet};
 use crate::photon::Photon;
 use crate::ray::Ray;
 use crate::scene::Scene;
 use glam::DVec3;
 use rand::Rng;
 
+// A roughness above 0 gives frosted glass, each ray is reflected and refracted off a
+//  randomly tilted facet of the surface
+// Light travelling inside the object is absorbed following the Beer-Lambert law, so
+//  thick parts of an object are more strongly colored than thin parts
+// Objects inside this one, or this inside other transparent objects, refract using the
+//  refractive indices on either side of each surface, tracked by the media on the ray
+// With dispersion each color channel is refracted with its own refractive index,
+//  splitting white light into a rainbow
 #[derive(Debug, Clone)]
 pub struct TransparentMaterial {
     refractive_index: f64,
+    roughness: f64,
+    // Fraction of each color absorbed per unit distance, black absorbs nothing
+    absorption: Color,
+    dispersion: Option<RefractiveIndex>,
+    // Where transparent objects overlap the one with the highest priority fills the
+    //  overlap, eg. a glass holding a liquid that slightly overlaps its walls
+    priority: u32,
 }
 
 impl<'a> TransparentMaterial {
     pub fn new(refractive_index: f64) -> Self {
-        TransparentMaterial { refractive_index }
+        TransparentMaterial::new_rough(refractive_index, 0.)
     }
 
-    // This function finds the exit point of a ray internal to a material
-    // This optionally returns a hit as it might not find one for objects not completely enclosed
-    // eg. planes
-    fn find_internal_hit(&self, ray: Ray, obj_index: usize, scene: &Scene) -> Option<Hit> {
-        let mut intersections = scene
-            .intersection(ray)
-            .filter(|h| {
-                // Find the first hit at least distance from this object with the same object index
-                (!h.get_dir()) && h.get_distance() > EPSILON && h.get_object_index() == obj_index
-            })
-            .collect::<Vec<Hit>>();
+    pub fn new_rough(refractive_index: f64, roughness: f64) -> Self {
+        assert!((0. ..=1.).contains(&roughness));
+        TransparentMaterial {
+            refractive_index,
+            roughness,
+            absorption: Color::new_black(),
+            dispersion: None,
+            priority: 0,
+        }
+    }
+
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    // Sets the priority used where this object overlaps other transparent objects
+    // Objects with the same priority use the one entered last
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    // Replaces the refractive index with one that changes with wavelength
+    pub fn with_dispersion(mut self, refractive_index: RefractiveIndex) -> Self {
+        self.refractive_index = refractive_index.at_channel(1);
+        self.dispersion = Some(refractive_index);
+        self
+    }
+
+    // This material as seen by a single color channel, with no dispersion
+    fn for_channel(&self, channel: usize) -> TransparentMaterial {
+        TransparentMaterial {
+            refractive_index: self
+                .dispersion
+                .map_or(self.refractive_index, |d| d.at_channel(channel)),
+            dispersion: None,
+            ..self.clone()
+        }
+    }
+
+    // Fraction of each color left after travelling a distance inside the object
+    fn attenuation(&self, distance: f64) -> Color {
+        Color::new(
+            (-self.absorption.red() * distance).exp(),
+            (-self.absorption.green() * distance).exp(),
+            (-self.absorption.blue() * distance).exp(),
+        )
+    }
 
-        intersections.sort_by(|l, r| l.get_distance().partial_cmp(&r.get_distance()).unwrap());
+    // The medium a ray travels through inside this object
+    fn medium(&self, hit: &Hit) -> Medium {
+        Medium::new(
+            hit.get_object_index(),
+            hit.get_csg_index(),
+            self.refractive_index,
+            self.priority,
+        )
+    }
+
+    // Where this object overlaps a transparent object with a higher priority its
+    //  surface is ignored, and the ray carries straight on
+    // This returns the continuing ray if the surface hit should be ignored
+    fn pass_through(&self, ray: Ray, hit: &Hit) -> Option<Ray> {
+        let media = ray.media();
+        let object_index = hit.get_object_index();
+
+        let (ignored, media) = if hit.get_dir() {
+            (
+                media
+                    .current()
+                    .is_some_and(|m| m.priority() > self.priority),
+                media.pushed(self.medium(hit)),
+            )
+        } else {
+            (
+                media
+                    .current()
+                    .is_some_and(|m| m.object_index() != object_index),
+                media.without(object_index),
+            )
+        };
 
-        intersections.first().cloned()
+        ignored.then(|| {
+            Ray::new(*hit.pos() + ray.direction() * EPSILON, ray.direction()).with_media(media)
+        })
     }
 
     // Finds the ray directions and powers of ray into a transparent material
     // This finds the ray direction and the Fresnel equations
+    // The refractive index on the other side of the surface comes from the media the
+    //  ray is in, so objects inside other transparent objects refract correctly
     fn find_rays(
         &self,
         normal: DVec3,
         incidence: DVec3,
         pos: DVec3,
+        media: MediumStack,
+        hit: &Hit,
         going_in: bool,
     ) -> (Option<Ray>, f64, Ray, f64) {
-        let refr_index = if going_in {
-            1. / self.refractive_index
+        // The media on the side the ray comes from and the side it refracts into
+        let (from_media, to_media) = if going_in {
+            (media, media.pushed(self.medium(hit)))
         } else {
-            self.refractive_index
+            (media, media.without(hit.get_object_index()))
         };
 
+        let refr_index = from_media.refractive_index() / to_media.refractive_index();
+
         let incidence = incidence.normalize();
         let normal = normal.normalize();
 
@@ -60,7 +157,8 @@ impl<'a> TransparentMaterial {
         // Finds the reflection direction
         let reflection_dir = incidence + 2. * (cos_t_i) * normal;
         let reflection_dir = reflection_dir.normalize();
-        let reflection_ray = Ray::new(pos + reflection_dir * EPSILON, reflection_dir);
+        let reflection_ray =
+            Ray::new(pos + reflection_dir * EPSILON, reflection_dir).with_media(from_media);
 
         let sin_2_t_i = refr_index.powi(2) * (1. - cos_t_i.powi(2));
 
@@ -78,10 +176,11 @@ impl<'a> TransparentMaterial {
         let r_floor = ((refr_index * cos_t_i - sqrt) / (refr_index * cos_t_i + sqrt)).powi(2);
         let r_bb = ((cos_t_i - refr_index * sqrt) / (cos_t_i + refr_index * sqrt)).powi(2);
 
-        let r_t_i = ((r_floor + r_bb) / 2.).max(0.).min(1.);
+        let r_t_i = ((r_floor + r_bb) / 2.).clamp(0., 1.);
         let t_t_i = 1. - r_t_i;
 
-        let refracted_ray = Ray::new(pos + refracted_dir * EPSILON, refracted_dir);
+        let refracted_ray =
+            Ray::new(pos + refracted_dir * EPSILON, refracted_dir).with_media(to_media);
 
         (Some(refracted_ray), t_t_i, reflection_ray, r_t_i)
     }
@@ -98,47 +197,112 @@ impl<'a> TransparentMaterial {
         recurse_depth: usize,
         obj_index: usize,
     ) -> Color {
-        if let Some(hit) = self.find_internal_hit(ray, obj_index, scene) {
-            // If it finds an internal hit then consider reflection and refraciton of the internal hit
-            let (trans_ray, trans_coeff, refl_ray, refl_coeff) =
-                self.find_rays(-*hit.normal(), ray.direction(), *hit.pos(), false);
-
-            // The reflected and transmitted coefficients
-            let refl_power = recurse_power * refl_coeff;
-            let trans_power = recurse_power * trans_coeff;
-
-            // Finds the result of the reflection part
-            // Skips if the coefficient is too small or if the recurse depth too great
-            let refl_part = if refl_power.max_val() > MIN_RECURSE_COEFFICIENT
-                && recurse_depth < MAX_RECURSE_DEPTH
-            {
-                self.calc_internal_ray(refl_ray, scene, refl_power, recurse_depth + 1, obj_index)
-                    * refl_coeff
-            } else {
-                Color::new_black()
-            };
-
-            // Finds the result of the transmitted part
-            // Skips if the coefficient is too small or if the recurse depth too great
-            let trans_part = if trans_power.max_val() > MIN_RECURSE_COEFFICIENT
-                && recurse_depth < MAX_RECURSE_DEPTH
-            {
-                scene
-                    .calc_ray(trans_ray.unwrap(), trans_power, recurse_depth + 1)
-                    .0
-                    * trans_coeff
-            } else {
-                Color::new_black()
-            };
-
-            refl_part + trans_part
-        } else {
+        // This optionally finds a hit as it might not find one for objects not completely
+        //  enclosed eg. planes
+        let Some(hit) = scene.next_hit(ray, EPSILON) else {
             // If no internal hit found, just send the ray into the scene
-            scene.calc_ray(ray, recurse_power, recurse_depth + 1).0
+            return scene.calc_ray(ray, recurse_power, recurse_depth + 1).0;
+        };
+
+        // Light is absorbed on the way to the internal hit
+        let attenuation = self.attenuation(hit.get_distance());
+        let recurse_power = recurse_power.piecewise_mul(&attenuation);
+
+        if hit.get_object_index() == obj_index && !hit.get_dir() {
+            self.calc_exit(ray, &hit, scene, recurse_power, recurse_depth)
+                .piecewise_mul(&attenuation)
+        } else {
+            // Another surface inside this object, such as a bubble or a liquid it holds
+            scene
+                .calc_hit(ray, &hit, recurse_power, recurse_depth + 1)
+                .piecewise_mul(&attenuation)
+        }
+    }
+
+    // Calculates the color of a ray inside the object reaching its surface
+    fn calc_exit(
+        &'a self,
+        ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_power: Color,
+        recurse_depth: usize,
+    ) -> Color {
+        // If it finds an internal hit then consider reflection and refraciton of the internal hit
+        // Rough surfaces use one random facet per internal hit, the rays entering
+        //  the object are already spread over several samples
+        let (trans_ray, trans_coeff, refl_ray, refl_coeff) = self.find_rays(
+            sample_facet(-*hit.normal(), ray.direction(), self.roughness),
+            ray.direction(),
+            *hit.pos(),
+            ray.media(),
+            hit,
+            false,
+        );
+
+        // The reflected and transmitted coefficients
+        let refl_power = recurse_power * refl_coeff;
+        let trans_power = recurse_power * trans_coeff;
+
+        // Finds the result of the reflection part
+        // Skips if the coefficient is too small or if the recurse depth too great
+        let refl_part = if refl_power.max_val() > MIN_RECURSE_COEFFICIENT
+            && recurse_depth < MAX_RECURSE_DEPTH
+        {
+            self.calc_internal_ray(
+                refl_ray,
+                scene,
+                refl_power,
+                recurse_depth + 1,
+                hit.get_object_index(),
+            ) * refl_coeff
+        } else {
+            Color::new_black()
+        };
+
+        // Finds the result of the transmitted part
+        // Skips if the coefficient is too small or if the recurse depth too great
+        let trans_part = if trans_power.max_val() > MIN_RECURSE_COEFFICIENT
+            && recurse_depth < MAX_RECURSE_DEPTH
+        {
+            scene
+                .calc_ray(trans_ray.unwrap(), trans_power, recurse_depth + 1)
+                .0
+                * trans_coeff
+        } else {
+            Color::new_black()
+        };
+
+        refl_part + trans_part
+    }
+
+    // Sends a photon leaving this object on into the scene
+    #[allow(clippy::too_many_arguments)]
+    fn continue_photon(
+        &self,
+        ray: Ray,
+        obj_index: usize,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+        caustic: bool,
+    ) -> Vec<Photon> {
+        if caustic {
+            scene
+                .calculate_caustic(&ray, obj_index, light_index, recurse_power, recurse_depth)
+                .map_or(vec![], |p| vec![p])
+            // Wrap the single photon into a vector for the recursion
+            // This inefficiency dramatically reduces the amount of logic required
+        } else {
+            scene.calculate_photon_ray(ray, light_index, recurse_depth + 1, recurse_power)
         }
     }
 
     // Calculates where a photon ends up from refraction
+    // The hit is either entering the object from outside or reaching its surface
+    //  from inside
+    #[allow(clippy::too_many_arguments)]
     fn calc_photon_internal(
         &self,
         view_ray: Ray,
@@ -147,7 +311,6 @@ impl<'a> TransparentMaterial {
         recurse_depth: usize,
         recurse_power: Color,
         light_index: usize,
-        inside: bool,
         caustic: bool,
     ) -> Vec<Photon> {
         // If exceeded recurse depth, skip
@@ -156,16 +319,43 @@ impl<'a> TransparentMaterial {
             return vec![];
         }
 
+        let obj_index = hit.get_object_index();
+        if let Some(ray) = self.pass_through(view_ray, hit) {
+            return self.continue_photon(
+                ray,
+                obj_index,
+                scene,
+                recurse_depth,
+                recurse_power,
+                light_index,
+                caustic,
+            );
+        }
+
+        // Light is absorbed on the way to an internal hit
+        let inside = !hit.get_dir();
+        let recurse_power = if inside {
+            recurse_power.piecewise_mul(&self.attenuation(hit.get_distance()))
+        } else {
+            recurse_power
+        };
+
         // Finds the result ray directions
         let (trans_ray, _, refl_ray, refl_coeff) = self.find_rays(
             // If inside the object reverse the normal direciton
-            if inside {
-                -*hit.normal()
-            } else {
-                *hit.normal()
-            },
+            sample_facet(
+                if inside {
+                    -*hit.normal()
+                } else {
+                    *hit.normal()
+                },
+                view_ray.direction(),
+                self.roughness,
+            ),
             view_ray.direction(),
             *hit.pos(),
+            view_ray.media(),
+            hit,
             !inside,
         );
 
@@ -173,103 +363,120 @@ impl<'a> TransparentMaterial {
         let mut rng = rand::thread_rng();
         let i: f64 = rng.gen_range((0.)..1.);
 
-        // If in the refleciton part
-        if i < refl_coeff {
-            // println!("Refl part");
-            // Reflection part
-            if inside {
-                let Some(new_hit) = self.find_internal_hit(refl_ray, hit.get_object_index(), scene) else {
-                    // If refracting internally and doesnt hit an outgoing wall, return nothing
-                    // println!("No internal hit");
-                    return vec![];
-                };
-
-                self.calc_photon_internal(
-                    refl_ray,
-                    &new_hit,
-                    scene,
-                    recurse_depth + 1,
-                    recurse_power,
-                    light_index,
-                    true,
-                    caustic,
-                )
-            } else if caustic {
-                // If refraction part AND this is a caustic
-                scene
-                    .calculate_caustic(
-                        &refl_ray,
-                        hit.get_object_index(),
-                        light_index,
-                        recurse_power,
-                        recurse_depth,
-                    )
-                    .map_or(vec![], |p| vec![p])
-                // Wrap the single photon into a vector for the recursion
-                // This inefficiency dramatically reduces the amount of logic required
-            } else {
-                // If refraction part AND this is NOT a caustic
-                scene.calculate_photon_ray(refl_ray, light_index, recurse_depth + 1, recurse_power)
-            }
+        // Either reflects, staying on the same side of the surface, or is transmitted
+        //  through it
+        let reflected = i < refl_coeff;
+        let ray = if reflected {
+            refl_ray
+        } else {
+            trans_ray.unwrap()
+        };
+
+        if reflected != inside {
+            // The photon is now outside the object so is cast into outside world
+            return self.continue_photon(
+                ray,
+                obj_index,
+                scene,
+                recurse_depth,
+                recurse_power,
+                light_index,
+                caustic,
+            );
+        }
+
+        // The photon is now inside the object, so find the next surface it reaches
+        let Some(new_hit) = scene.next_hit(ray, EPSILON) else {
+            // If refracting internally and doesnt hit an outgoing wall, return nothing
+            return vec![];
+        };
+
+        if new_hit.get_object_index() == obj_index && !new_hit.get_dir() {
+            // Find the result of firing another ray internally
+            self.calc_photon_internal(
+                ray,
+                &new_hit,
+                scene,
+                recurse_depth + 1,
+                recurse_power,
+                light_index,
+                caustic,
+            )
         } else {
-            // Transparent part
-            if inside {
-                // If inside, then the transparent part is outside the object to cast photon into outside world
-                if caustic {
-                    scene
-                        .calculate_caustic(
-                            &trans_ray.unwrap(),
-                            hit.get_object_index(),
-                            light_index,
-                            recurse_power,
-                            recurse_depth,
-                        )
-                        .map_or(vec![], |p| vec![p])
-                    // Otherwise throw the photon ray into the world
+            // Another surface inside this object, which is left to the scene after the
+            //  light absorbed on the way there
+            self.continue_photon(
+                ray,
+                obj_index,
+                scene,
+                recurse_depth,
+                recurse_power.piecewise_mul(&self.attenuation(new_hit.get_distance())),
+                light_index,
+                caustic,
+            )
+        }
+    }
+
+    // Finds the color of a ray hitting the surface, either from outside or from inside
+    //  after passing through other objects within this one
+    fn compute_surface(
+        &'a self,
+        view_ray: Ray,
+        hit: &'a Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        if let Some(ray) = self.pass_through(view_ray, hit) {
+            return scene.calc_ray(ray, recurse_power, recurse_depth + 1).0;
+        }
+
+        // Smooth surfaces only need the one perfect reflection and refraction
+        let samples = if self.roughness > 0. {
+            glossy_sample_count(DEFAULT_GLOSSY_SAMPLES, recurse_depth)
+        } else {
+            1
+        };
+
+        (0..samples)
+            .map(|_| {
+                if hit.get_dir() {
+                    self.compute_sample(view_ray, hit, scene, recurse_depth, recurse_power)
                 } else {
-                    scene.calculate_photon_ray(
-                        trans_ray.unwrap(),
-                        light_index,
-                        recurse_depth + 1,
-                        recurse_power,
+                    // Light is absorbed on the way to the surface
+                    let attenuation = self.attenuation(hit.get_distance());
+                    self.calc_exit(
+                        view_ray,
+                        hit,
+                        scene,
+                        recurse_power.piecewise_mul(&attenuation),
+                        recurse_depth,
                     )
+                    .piecewise_mul(&attenuation)
                 }
-            } else {
-                // If outside, then cast photon internally
-                let Some(new_hit) = self.find_internal_hit(trans_ray.unwrap(), hit.get_object_index(), scene) else {
-                    // If doesnt find another material, return nothing
-                    return vec![];
-                };
-
-                // Find the result of firing another ray internally
-                self.calc_photon_internal(
-                    trans_ray.unwrap(),
-                    &new_hit,
-                    scene,
-                    recurse_depth + 1,
-                    recurse_power,
-                    light_index,
-                    true,
-                    caustic,
-                )
-            }
-        }
+            })
+            .sum::<Color>()
+            * (1. / samples as f64)
     }
-}
 
-impl Material for TransparentMaterial {
-    fn compute<'a>(
+    // Finds the color of one reflected and refracted ray pair off the surface
+    fn compute_sample(
         &'a self,
         view_ray: Ray,
         hit: &'a Hit,
-        _: Color,
         scene: &Scene,
         recurse_depth: usize,
         recurse_power: Color,
     ) -> Color {
         // Calculates ray directions and fresnel coefficients for the reflected and transmitted part
-        let (trans_ray, trans_coeff, refl_ray, refl_coeff) =
-            self.find_rays(*hit.normal(), view_ray.direction(), *hit.pos(), true);
+        let (trans_ray, trans_coeff, refl_ray, refl_coeff) = self.find_rays(
+            sample_facet(*hit.normal(), view_ray.direction(), self.roughness),
+            view_ray.direction(),
+            *hit.pos(),
+            view_ray.media(),
+            hit,
+            true,
+        );
 
         let refl_power = recurse_power * refl_coeff;
         let trans_power = recurse_power * trans_coeff;
@@ -300,6 +507,31 @@ impl Material for TransparentMaterial {
 
         refl_part + trans_part
     }
+}
+
+impl Material for TransparentMaterial {
+    fn compute<'a>(
+        &'a self,
+        view_ray: Ray,
+        hit: &'a Hit,
+        _: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        // Dispersive materials trace each color channel separately
+        if self.dispersion.is_some() {
+            (0..3)
+                .map(|channel| {
+                    self.for_channel(channel)
+                        .compute_surface(view_ray, hit, scene, recurse_depth, recurse_power)
+                        .piecewise_mul(&channel_mask(channel))
+                })
+                .sum()
+        } else {
+            self.compute_surface(view_ray, hit, scene, recurse_depth, recurse_power)
+        }
+    }
 
     fn compute_photon(
         &self,
@@ -310,6 +542,19 @@ impl Material for TransparentMaterial {
         recurse_power: Color,
         light_index: usize,
     ) -> Vec<Photon> {
+        // Dispersive materials send each photon as a random color channel
+        if self.dispersion.is_some() {
+            let channel = rand::thread_rng().gen_range(0..3);
+            return self.for_channel(channel).compute_photon(
+                view_ray,
+                hit,
+                scene,
+                recurse_depth,
+                recurse_power.piecewise_mul(&channel_mask(channel)) * 3.,
+                light_index,
+            );
+        }
+
         // Calls the recursive method
         self.calc_photon_internal(
             view_ray,
@@ -319,7 +564,6 @@ impl Material for TransparentMaterial {
             recurse_power,
             light_index,
             false,
-            false,
         )
     }
 
@@ -328,6 +572,15 @@ impl Material for TransparentMaterial {
         true
     }
 
+    fn is_transparent(&self) -> bool {
+        true
+    }
+
+    // Shadow rays pass straight through, the bending of the light is left to the caustics
+    fn shadow_transmittance(&self, _: &Hit) -> Color {
+        Color::new_grey(1.)
+    }
+
     fn compute_caustic_ray(
         &self,
         view_ray: Ray,
@@ -337,6 +590,20 @@ impl Material for TransparentMaterial {
         light_index: usize,
         color: Color,
     ) -> Option<Photon> {
+        // Dispersive materials send each caustic as a random color channel, so the
+        //  colors spread out into a rainbow
+        if self.dispersion.is_some() {
+            let channel = rand::thread_rng().gen_range(0..3);
+            return self.for_channel(channel).compute_caustic_ray(
+                view_ray,
+                hit,
+                scene,
+                recurse_depth,
+                light_index,
+                color.piecewise_mul(&channel_mask(channel)) * 3.,
+            );
+        }
+
         // Return the first photon from sending in a caustic ray
         self.calc_photon_internal(
             view_ray,
@@ -345,7 +612,6 @@ impl Material for TransparentMaterial {
             recurse_depth,
             color,
             light_index,
-            false,
             true,
         )
         .into_iter()
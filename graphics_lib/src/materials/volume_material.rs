@@ -0,0 +1,108 @@
+use crate::color::Color;
+use crate::constants::{EPSILON, MAX_RECURSE_DEPTH};
+use crate::hit::Hit;
+use crate::materials::material::Material;
+use crate::medium::Medium;
+use crate::photon::Photon;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::volumes::volume::Volume;
+
+// This material fills an object with a volume, like a cloud of smoke or a glass of milk
+// The surface itself is invisible, rays pass straight through it and the scene
+//  scatters them through the volume until they leave the object
+// The object must be closed, rays leaving it are found the same way TransparentMaterial
+//  finds them
+#[derive(Debug)]
+pub struct VolumeMaterial {
+    volume: Box<dyn Volume + Sync + Send>,
+}
+
+impl VolumeMaterial {
+    pub fn new(volume: Box<dyn Volume + Sync + Send>) -> Self {
+        VolumeMaterial { volume }
+    }
+
+    // The ray carrying on through the surface, inside the volume if it is entering
+    fn continue_ray(ray: Ray, hit: &Hit) -> Ray {
+        let media = if hit.get_dir() {
+            ray.media().pushed(Medium::new(
+                hit.get_object_index(),
+                hit.get_csg_index(),
+                ray.media().refractive_index(),
+                0,
+            ))
+        } else {
+            ray.media().without(hit.get_object_index())
+        };
+
+        Ray::new(*hit.pos() + ray.direction() * EPSILON, ray.direction()).with_media(media)
+    }
+}
+
+impl Material for VolumeMaterial {
+    fn compute(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        _: Color,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Color {
+        if recurse_depth >= MAX_RECURSE_DEPTH {
+            return Color::new_black();
+        }
+
+        scene
+            .calc_ray(
+                VolumeMaterial::continue_ray(view_ray, hit),
+                recurse_power,
+                recurse_depth + 1,
+            )
+            .0
+    }
+
+    fn compute_photon(
+        &self,
+        view_ray: Ray,
+        hit: &Hit,
+        scene: &Scene,
+        recurse_depth: usize,
+        recurse_power: Color,
+        light_index: usize,
+    ) -> Vec<Photon> {
+        scene.calculate_photon_ray(
+            VolumeMaterial::continue_ray(view_ray, hit),
+            light_index,
+            recurse_depth,
+            recurse_power,
+        )
+    }
+
+    fn needs_caustic(&self) -> bool {
+        false
+    }
+
+    // Shadow rays are dimmed by the scene following them through the volume instead
+    fn shadow_transmittance(&self, _: &Hit) -> Color {
+        Color::new_grey(1.)
+    }
+
+    fn volume(&self) -> Option<&(dyn Volume + Sync + Send)> {
+        Some(self.volume.as_ref())
+    }
+
+    fn compute_caustic_ray(
+        &self,
+        _view_ray: Ray,
+        _hit: &Hit,
+        _scene: &Scene,
+        _recurse_depth: usize,
+        _light_index: usize,
+        _: Color,
+    ) -> Option<Photon> {
+        // Does not retransmit caustics
+        None
+    }
+}
@@ -0,0 +1,170 @@
+use crate::constants::MAX_NESTED_MEDIA;
+
+// A transparent object or volume a ray is travelling inside of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Medium {
+    object_index: usize,
+    // Part of the CSG tree the object was entered through, to find its material
+    csg_index: usize,
+    refractive_index: f64,
+    // Where transparent objects overlap the one with the highest priority is used
+    priority: u32,
+}
+
+impl Medium {
+    pub(crate) fn new(
+        object_index: usize,
+        csg_index: usize,
+        refractive_index: f64,
+        priority: u32,
+    ) -> Self {
+        Medium {
+            object_index,
+            csg_index,
+            refractive_index,
+            priority,
+        }
+    }
+
+    pub(crate) fn object_index(&self) -> usize {
+        self.object_index
+    }
+
+    pub(crate) fn csg_index(&self) -> usize {
+        self.csg_index
+    }
+
+    pub(crate) fn priority(&self) -> u32 {
+        self.priority
+    }
+}
+
+// The transparent objects and volumes a ray is inside of, in the order they were entered
+// This is a fixed size so rays can still be copied, objects nested deeper than
+//  MAX_NESTED_MEDIA are treated as if they were air
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MediumStack {
+    media: [Medium; MAX_NESTED_MEDIA],
+    len: usize,
+}
+
+impl MediumStack {
+    // A ray in air, not inside any objects
+    pub(crate) fn new() -> Self {
+        MediumStack {
+            media: [Medium::new(0, 0, 1., 0); MAX_NESTED_MEDIA],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn contains(&self, object_index: usize) -> bool {
+        self.media[..self.len]
+            .iter()
+            .any(|m| m.object_index == object_index)
+    }
+
+    // The media after entering an object
+    pub(crate) fn pushed(mut self, medium: Medium) -> Self {
+        if self.len < MAX_NESTED_MEDIA && !self.contains(medium.object_index) {
+            self.media[self.len] = medium;
+            self.len += 1;
+        }
+        self
+    }
+
+    // The media after leaving an object
+    pub(crate) fn without(mut self, object_index: usize) -> Self {
+        if let Some(i) = self.media[..self.len]
+            .iter()
+            .position(|m| m.object_index == object_index)
+        {
+            self.media.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+        }
+        self
+    }
+
+    // The medium the ray is actually travelling through, the one with the highest
+    //  priority, or the most recently entered if several have the same priority
+    pub(crate) fn current(&self) -> Option<Medium> {
+        self.media[..self.len]
+            .iter()
+            .max_by_key(|m| m.priority)
+            .copied()
+    }
+
+    // Refractive index of the medium the ray is travelling through, 1 for air
+    pub(crate) fn refractive_index(&self) -> f64 {
+        self.current().map_or(1., |m| m.refractive_index)
+    }
+}
+
+impl Default for MediumStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn air_by_default() {
+        let media = MediumStack::new();
+        assert!(media.is_empty());
+        assert_eq!(media.current(), None);
+        assert_eq!(media.refractive_index(), 1.);
+    }
+
+    #[test]
+    fn push_and_remove() {
+        let glass = Medium::new(1, 0, 1.5, 0);
+        let water = Medium::new(2, 0, 1.33, 0);
+        let media = MediumStack::new().pushed(glass).pushed(water);
+
+        assert!(media.contains(1) && media.contains(2));
+        // With the same priority the most recently entered is used
+        assert_eq!(media.current(), Some(water));
+
+        let media = media.without(2);
+        assert!(!media.contains(2));
+        assert_eq!(media.refractive_index(), 1.5);
+
+        // Removing an object the ray is not inside does nothing
+        assert_eq!(media.without(5).current(), Some(glass));
+        assert!(media.without(1).is_empty());
+    }
+
+    #[test]
+    fn entering_twice_is_ignored() {
+        let glass = Medium::new(1, 0, 1.5, 0);
+        let media = MediumStack::new().pushed(glass).pushed(glass);
+        assert!(media.without(1).is_empty());
+    }
+
+    #[test]
+    fn highest_priority_is_used() {
+        let water = Medium::new(1, 0, 1.33, 0);
+        let glass = Medium::new(2, 0, 1.5, 1);
+        let ice = Medium::new(3, 0, 1.31, 0);
+        let media = MediumStack::new().pushed(water).pushed(glass).pushed(ice);
+
+        assert_eq!(media.current(), Some(glass));
+        assert_eq!(media.without(2).current(), Some(ice));
+    }
+
+    #[test]
+    fn too_deep_is_air() {
+        let media = (0..MAX_NESTED_MEDIA + 2).fold(MediumStack::new(), |media, i| {
+            media.pushed(Medium::new(i, 0, 1.5, 0))
+        });
+
+        assert!(media.contains(MAX_NESTED_MEDIA - 1));
+        assert!(!media.contains(MAX_NESTED_MEDIA));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::scene::Scene;
+use glam::DVec3;
+use rand::Rng;
+use std::f64::consts::PI;
+
+// Helper functions for microfacet surfaces, rough surfaces made of many tiny mirrors
+// Uses the GGX (Trowbridge-Reitz) distribution of facet normals with Smith shadowing
+// Alpha is the width of the distribution, the square of the artist friendly roughness
+
+// Smallest alpha used, perfectly smooth surfaces would divide by zero
+const MIN_ALPHA: f64 = 1.0E-3;
+
+// Converts a roughness between 0 and 1 into the alpha of the distribution
+pub(crate) fn roughness_to_alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+// Mirrors a direction about a normal
+pub(crate) fn reflect(dir: DVec3, normal: DVec3) -> DVec3 {
+    dir - 2. * dir.dot(normal) * normal
+}
+
+// Density of facets with a normal at an angle to the surface normal,
+//  given the cosine of that angle
+pub(crate) fn ggx_distribution(cos_theta: f64, alpha: f64) -> f64 {
+    if cos_theta <= 0. {
+        return 0.;
+    }
+    let alpha2 = alpha * alpha;
+    let denominator = cos_theta * cos_theta * (alpha2 - 1.) + 1.;
+    alpha2 / (PI * denominator * denominator)
+}
+
+// Fraction of facets visible from a direction, given the cosine to the surface normal
+pub(crate) fn smith_g1(cos_theta: f64, alpha: f64) -> f64 {
+    if cos_theta <= 0. {
+        return 0.;
+    }
+    let alpha2 = alpha * alpha;
+    2. * cos_theta / (cos_theta + (alpha2 + (1. - alpha2) * cos_theta * cos_theta).sqrt())
+}
+
+// Fraction of facets both lit and visible
+pub(crate) fn smith_g(cos_view: f64, cos_light: f64, alpha: f64) -> f64 {
+    smith_g1(cos_view, alpha) * smith_g1(cos_light, alpha)
+}
+
+// Schlick's approximation of the fraction of light reflected, given the reflectance
+//  looking straight on and the cosine of the angle to the facet
+pub(crate) fn schlick_fresnel(f0: Color, cos_theta: f64) -> Color {
+    let t = (1. - cos_theta.clamp(0., 1.)).powi(5);
+    f0 * (1. - t) + Color::new_grey(t)
+}
+
+// Fraction of light reflected by a metal, given the cosine of the angle to the facet
+// Metals have a complex index of refraction n + ik for each color channel, where k
+//  is how strongly the metal absorbs light
+pub(crate) fn conductor_fresnel(n: DVec3, k: DVec3, cos_theta: f64) -> Color {
+    let cos2 = cos_theta.clamp(0., 1.).powi(2);
+    let sin2 = 1. - cos2;
+
+    let channel = |n: f64, k: f64| {
+        let t0 = n * n - k * k - sin2;
+        let a2b2 = (t0 * t0 + 4. * n * n * k * k).sqrt();
+        let a = (0.5 * (a2b2 + t0)).max(0.).sqrt();
+
+        // Perpendicular and parallel polarised parts
+        let t1 = a2b2 + cos2;
+        let t2 = 2. * a * cos2.sqrt();
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        ((rs + rp) / 2.).clamp(0., 1.)
+    };
+
+    Color::new(channel(n.x, k.x), channel(n.y, k.y), channel(n.z, k.z))
+}
+
+// Light from each light in the scene reflected towards the viewer, given the light
+//  reflected from light arriving along a direction
+pub(crate) fn direct_light(hit: &Hit, scene: &Scene, reflected: impl Fn(DVec3) -> Color) -> Color {
+    scene
+        .get_lights()
+        .iter()
+        .enumerate()
+        .flat_map(|(i, light)| light.get_samples(*hit.pos(), scene, i))
+        .fold(Color::new_black(), |c, (intensity, dir)| {
+            c + intensity.piecewise_mul(&reflected(-dir))
+        })
+}
+
+// Light reflected towards the viewer by the facets from light arriving along a
+//  direction, including the cosine of the light
+// Fresnel gives the reflectance from the cosine of the angle to the facet
+// Multiplied by PI to match the brightness of DiffuseMaterial
+pub(crate) fn ggx_specular(
+    normal: DVec3,
+    view: DVec3,
+    light: DVec3,
+    alpha: f64,
+    fresnel: impl Fn(f64) -> Color,
+) -> Color {
+    let cos_view = normal.dot(view);
+    let cos_light = normal.dot(light);
+    if cos_view <= 0. || cos_light <= 0. {
+        return Color::new_black();
+    }
+
+    let half = (view + light).normalize();
+    fresnel(view.dot(half))
+        * (ggx_distribution(normal.dot(half), alpha) * smith_g(cos_view, cos_light, alpha) * PI
+            / (4. * cos_view))
+}
+
+// Number of rays to spread a blurry reflection or refraction over
+// Halves with each recursion so reflections of reflections stay cheap
+pub(crate) fn glossy_sample_count(samples: usize, recurse_depth: usize) -> usize {
+    samples
+        .checked_shr(recurse_depth as u32)
+        .unwrap_or(0)
+        .max(1)
+}
+
+// Picks a facet normal for a rough surface hit by a ray travelling along a direction
+// Smooth surfaces, and facets the ray cannot hit, use the surface normal
+pub(crate) fn sample_facet(normal: DVec3, incidence: DVec3, roughness: f64) -> DVec3 {
+    if roughness <= 0. {
+        return normal;
+    }
+
+    let facet = sample_ggx_normal(normal, roughness_to_alpha(roughness));
+    if facet.dot(incidence) < 0. {
+        facet
+    } else {
+        normal
+    }
+}
+
+// Picks a random facet normal in proportion to its density times its cosine
+//  to the surface normal
+pub(crate) fn sample_ggx_normal(normal: DVec3, alpha: f64) -> DVec3 {
+    let mut rng = rand::thread_rng();
+    let u: f64 = rng.gen_range(0. ..1.);
+    let phi: f64 = rng.gen_range(0. ..2. * PI);
+
+    let theta = (alpha * (u / (1. - u)).sqrt()).atan();
+    let (a, b) = normal.any_orthonormal_pair();
+
+    (normal * theta.cos() + (a * phi.cos() + b * phi.sin()) * theta.sin()).normalize()
+}
+
+// Weight of a reflection sampled with sample_ggx_normal, the BRDF times the cosine
+//  divided by the probability of picking the direction, without Fresnel
+pub(crate) fn sampled_reflection_weight(
+    cos_view: f64,
+    cos_light: f64,
+    cos_facet: f64,
+    view_dot_facet: f64,
+    alpha: f64,
+) -> f64 {
+    if cos_view <= 0. || cos_light <= 0. || cos_facet <= 0. {
+        return 0.;
+    }
+    smith_g(cos_view, cos_light, alpha) * view_dot_facet / (cos_view * cos_facet)
+}
+
+// Picks a direction reflected off a random facet, and its weight times the Fresnel
+//  reflectance from the cosine of the angle to the facet
+// Gives None for directions the surface cannot reflect into
+pub(crate) fn sample_ggx_reflection(
+    view: DVec3,
+    normal: DVec3,
+    alpha: f64,
+    fresnel: impl Fn(f64) -> Color,
+) -> Option<(DVec3, Color)> {
+    let facet = sample_ggx_normal(normal, alpha);
+    let dir = reflect(-view, facet);
+
+    let weight = sampled_reflection_weight(
+        normal.dot(view),
+        normal.dot(dir),
+        normal.dot(facet),
+        view.dot(facet),
+        alpha,
+    );
+    if weight <= 0. {
+        return None;
+    }
+
+    Some((dir, fresnel(view.dot(facet)) * weight))
+}
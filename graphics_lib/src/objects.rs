@@ -1,3 +1,4 @@
+pub mod axis_aligned_box;
 pub mod csg;
 pub mod cube;
 pub mod object;
@@ -0,0 +1,88 @@
+use crate::hit::Hit;
+use crate::objects::object::Object;
+use crate::primitives::aabb::AabbPrimitive;
+use crate::primitives::primitive::Primitive;
+use crate::scene::Scene;
+use glam::{DAffine3, DVec3};
+
+// A box lined up with the axes between two opposite corners, a single primitive
+//  the BVH can bound exactly
+// Useful to hold volumes such as a GridVolume, using its bounds as the corners
+#[derive(Debug)]
+pub struct AxisAlignedBox {
+    min: DVec3,
+    max: DVec3,
+    material: usize,
+    csg_index: usize,
+}
+
+impl AxisAlignedBox {
+    pub fn new(min: DVec3, max: DVec3, material: usize) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+            material,
+            csg_index: 0,
+        }
+    }
+
+    // The eight corners of the box
+    fn corners(&self) -> Vec<DVec3> {
+        (0..8)
+            .map(|i| {
+                DVec3::new(
+                    if i & 1 == 0 { self.min.x } else { self.max.x },
+                    if i & 2 == 0 { self.min.y } else { self.max.y },
+                    if i & 4 == 0 { self.min.z } else { self.max.z },
+                )
+            })
+            .collect()
+    }
+}
+
+impl Object for AxisAlignedBox {
+    // The box stays lined up with the axes, so rotating it grows it to fit around
+    //  the rotated corners
+    // Volumes in the material, such as a GridVolume, do not move with the box
+    fn apply_transform(&mut self, t: &DAffine3) {
+        let (min, max) = self
+            .corners()
+            .into_iter()
+            .map(|c| t.transform_point3(c))
+            .fold(
+                (DVec3::splat(f64::INFINITY), DVec3::splat(-f64::INFINITY)),
+                |(min, max), c| (min.min(c), max.max(c)),
+            );
+        self.min = min;
+        self.max = max;
+    }
+
+    fn get_material(&self, _: &Hit) -> usize {
+        self.material
+    }
+
+    fn set_csg_index(&mut self, csg_index: usize) {
+        self.csg_index = csg_index;
+    }
+
+    fn primitives(&self, obj_index: usize) -> Vec<Box<dyn Primitive + Sync + Send>> {
+        vec![Box::new(AabbPrimitive::new(
+            self.min,
+            self.max,
+            obj_index,
+            self.csg_index,
+        ))]
+    }
+
+    fn filter_hits(&self, hits: Vec<Hit>, _: usize) -> Vec<Hit> {
+        hits
+    }
+
+    fn get_caustic_bounds(&self) -> (DVec3, DVec3) {
+        (self.min, self.max)
+    }
+
+    fn needs_caustic(&self, scene: &Scene) -> bool {
+        scene.material_needs_caustic(self.material)
+    }
+}
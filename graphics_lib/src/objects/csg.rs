@@ -88,7 +88,7 @@ impl Object for CSG {
                     h.get_object_index() == index
                         && is_node_left(self.csg_index, h.get_csg_index()) == Some(true)
                 })
-                .map_or(false, |h| !h.get_dir())
+                .is_some_and(|h| !h.get_dir())
         };
         let mut inside_right = {
             hits.iter()
@@ -96,7 +96,7 @@ impl Object for CSG {
                     h.get_object_index() == index
                         && is_node_left(self.csg_index, h.get_csg_index()) == Some(false)
                 })
-                .map_or(false, |h| !h.get_dir())
+                .is_some_and(|h| !h.get_dir())
         };
 
         let mut prev_inside_left = inside_left;
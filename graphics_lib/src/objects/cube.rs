@@ -3,7 +3,7 @@ use crate::objects::object::Object;
 use crate::primitives::primitive::Primitive;
 use crate::primitives::triangle::TrianglePrimitive;
 use crate::scene::Scene;
-use glam::{DAffine3, DVec3};
+use glam::{DAffine3, DVec2, DVec3};
 
 #[derive(Debug)]
 pub struct Cube {
@@ -25,8 +25,8 @@ impl Cube {
 
     // Defines the vertices of the triangles making up
     //   a cube of side length 1 at the origin
-    fn get_triangles(&self) -> Vec<(DVec3, DVec3, DVec3)> {
-        let triangles: Vec<(DVec3, DVec3, DVec3)> = vec![
+    fn get_local_triangles() -> Vec<(DVec3, DVec3, DVec3)> {
+        vec![
             (
                 DVec3::new(-0.5, -0.5, -0.5),
                 DVec3::new(-0.5, 0.5, 0.5),
@@ -87,9 +87,12 @@ impl Cube {
                 DVec3::new(0.5, 0.5, 0.5),
                 DVec3::new(0.5, -0.5, 0.5),
             ), // +z side
-        ];
+        ]
+    }
 
-        triangles
+    // The triangles of the cube after it has been transformed
+    fn get_triangles(&self) -> Vec<(DVec3, DVec3, DVec3)> {
+        Cube::get_local_triangles()
             .into_iter()
             .map(|(p1, p2, p3)| {
                 (
@@ -120,19 +123,23 @@ impl Object for Cube {
         let triangles: Vec<Box<dyn Primitive + Sync + Send>> = self
             .get_triangles()
             .into_iter()
-            .map::<Box<dyn Primitive + Sync + Send>, _>(|(p1, p2, p3)| {
-                Box::new(TrianglePrimitive::new(
-                    p1,
-                    p2,
-                    p3,
-                    (p3 - p1).cross(p2 - p1).normalize(),
-                    DVec3::new(0., 0., 0.),
-                    DVec3::new(0., 0., 0.),
-                    DVec3::new(0., 0., 0.),
-                    false,
-                    obj_index,
-                    self.csg_index,
-                ))
+            .zip(Cube::get_local_triangles())
+            .map::<Box<dyn Primitive + Sync + Send>, _>(|((p1, p2, p3), local)| {
+                Box::new(
+                    TrianglePrimitive::new(
+                        p1,
+                        p2,
+                        p3,
+                        (p3 - p1).cross(p2 - p1).normalize(),
+                        DVec3::new(0., 0., 0.),
+                        DVec3::new(0., 0., 0.),
+                        DVec3::new(0., 0., 0.),
+                        false,
+                        obj_index,
+                        self.csg_index,
+                    )
+                    .with_uvs(face_uvs(local)),
+                )
             })
             .collect();
         triangles
@@ -159,3 +166,17 @@ impl Object for Cube {
         scene.material_needs_caustic(self.material)
     }
 }
+
+// Each face of the cube is covered by the whole texture once
+// Finds the axis the face is flat along and uses the other two coordinates
+fn face_uvs((p1, p2, p3): (DVec3, DVec3, DVec3)) -> [DVec2; 3] {
+    let to_uv: fn(DVec3) -> DVec2 = if p1.x == p2.x && p2.x == p3.x {
+        |p| DVec2::new(p.z, p.y)
+    } else if p1.y == p2.y && p2.y == p3.y {
+        |p| DVec2::new(p.x, p.z)
+    } else {
+        |p| DVec2::new(p.x, p.y)
+    };
+
+    [p1, p2, p3].map(|p| to_uv(p) + DVec2::new(0.5, 0.5))
+}
@@ -24,6 +24,7 @@ pub trait Object: Debug {
     fn filter_hits(&self, hits: Vec<Hit>, index: usize) -> Vec<Hit>;
 
     // Gets a bounding box for the object to fire rays within
+    // Unbounded objects give an infinite box and get no caustics
     fn get_caustic_bounds(&self) -> (DVec3, DVec3);
 
     // Works out if an object needs caustics or not
@@ -3,7 +3,7 @@ use crate::objects::object::Object;
 use crate::primitives::primitive::Primitive;
 use crate::primitives::triangle::TrianglePrimitive;
 use crate::scene::Scene;
-use glam::{DAffine3, DVec3};
+use glam::{DAffine3, DVec2, DVec3};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -23,6 +23,8 @@ struct Vertex {
     p: DVec3,
     // Normal may be calculated for smoothing
     normal: Option<DVec3>,
+    // Texture coordinates, if the file has them
+    uv: Option<DVec2>,
 }
 
 impl Vertex {
@@ -76,6 +78,7 @@ pub struct PolyMesh {
 
 impl PolyMesh {
     // Makes a Polymesh from a file
+    // Vertex lines may optionally have texture coordinates after the position
     // Note this can fail!
     pub fn from_file(
         file: BufReader<File>,
@@ -137,7 +140,7 @@ impl PolyMesh {
                 .map_err(|_| "Faces number malformed.")?
         };
 
-        let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices as usize);
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
 
         for i in 0..num_vertices {
             let line = lines
@@ -145,31 +148,41 @@ impl PolyMesh {
                 .ok_or("Vertex line missing")?
                 .map_err(|_| "Cannot read vertex line.")?;
 
-            let mut split_line = line.split(' ');
+            let mut split_line = line.split_whitespace();
             vertices.push(Vertex {
                 p: DVec3::new(
                     split_line
                         .next()
                         .ok_or("Missing vertex (1)")?
                         .parse()
-                        .map_err(|e: _| format!("Malformed coordinate {}.1", i))?,
+                        .map_err(|_| format!("Malformed coordinate {}.1", i))?,
                     split_line
                         .next()
                         .ok_or("Missing vertex (2)")?
                         .parse()
-                        .map_err(|e: _| format!("Malformed coordinate {}.2", i))?,
+                        .map_err(|_| format!("Malformed coordinate {}.2", i))?,
                     split_line
                         .next()
                         .ok_or("Missing vertex (3)")?
                         .parse()
-                        .map_err(|e: _| format!("Malformed coordinate {}.3", i))?,
+                        .map_err(|_| format!("Malformed coordinate {}.3", i))?,
                 ),
                 triangles: vec![],
                 normal: None,
+                uv: match (split_line.next(), split_line.next()) {
+                    (Some(u), Some(v)) => Some(DVec2::new(
+                        u.parse()
+                            .map_err(|_| format!("Malformed texture coordinate {}.1", i))?,
+                        v.parse()
+                            .map_err(|_| format!("Malformed texture coordinate {}.2", i))?,
+                    )),
+                    (None, None) => None,
+                    _ => return Err(format!("Missing texture coordinate {}.2", i)),
+                },
             });
         }
 
-        let mut faces = Vec::with_capacity(num_faces as usize);
+        let mut faces = Vec::with_capacity(num_faces);
 
         // Populates faces vector
         for i in 0..num_faces {
@@ -264,18 +277,25 @@ impl Object for PolyMesh {
                 let vb = self.vertices.get(t.bn).unwrap();
                 let vc = self.vertices.get(t.cn).unwrap();
 
-                Box::new(TrianglePrimitive::new(
-                    va.p,
-                    vb.p,
-                    vc.p,
-                    t.n,
-                    va.normal.unwrap(),
-                    vb.normal.unwrap(),
-                    vc.normal.unwrap(),
-                    self.smoothing,
-                    obj_index,
-                    self.csg_index,
-                ))
+                Box::new(
+                    TrianglePrimitive::new(
+                        va.p,
+                        vb.p,
+                        vc.p,
+                        t.n,
+                        va.normal.unwrap(),
+                        vb.normal.unwrap(),
+                        vc.normal.unwrap(),
+                        self.smoothing,
+                        obj_index,
+                        self.csg_index,
+                    )
+                    .with_uvs([
+                        va.uv.unwrap_or(DVec2::ZERO),
+                        vb.uv.unwrap_or(DVec2::ZERO),
+                        vc.uv.unwrap_or(DVec2::ZERO),
+                    ]),
+                )
             })
             .collect()
     }
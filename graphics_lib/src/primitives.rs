@@ -1,3 +1,4 @@
+pub mod aabb;
 pub mod plane;
 pub mod primitive;
 pub mod quadratic;
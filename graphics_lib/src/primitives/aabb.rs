@@ -0,0 +1,235 @@
+use crate::hit::Hit;
+use crate::primitives::primitive::Primitive;
+use crate::ray::Ray;
+use bvh::aabb::{Bounded, AABB};
+use bvh::bounding_hierarchy::BHShape;
+use glam::DVec3;
+use rand::Rng;
+
+// A box lined up with the axes, between two opposite corners
+#[derive(Clone, Debug)]
+pub struct AabbPrimitive {
+    min: DVec3,
+    max: DVec3,
+    node_index: usize,
+    obj_index: usize,
+    csg_index: usize,
+}
+
+impl AabbPrimitive {
+    pub(crate) fn new(min: DVec3, max: DVec3, obj_index: usize, csg_index: usize) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+            node_index: 0,
+            obj_index,
+            csg_index,
+        }
+    }
+}
+
+// The outward normal of the face of a box an axis goes through, on the side a
+//  direction points to
+fn face_normal(axis: usize, dir: f64) -> DVec3 {
+    let mut normal = DVec3::ZERO;
+    normal[axis] = dir.signum();
+    normal
+}
+
+impl BHShape for AabbPrimitive {
+    fn set_bh_node_index(&mut self, n: usize) {
+        self.node_index = n
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Bounded for AabbPrimitive {
+    fn aabb(&self) -> AABB {
+        AABB::with_bounds(self.min.as_vec3(), self.max.as_vec3())
+    }
+}
+
+impl Primitive for AabbPrimitive {
+    fn get_object(&self) -> usize {
+        self.obj_index
+    }
+
+    fn get_csg_index(&self) -> usize {
+        self.csg_index
+    }
+
+    // Uses the slab method, the ray is inside the box where it is between the two
+    //  faces on every axis
+    fn intersection(&self, ray: &Ray) -> Vec<Hit> {
+        let inverse = ray.direction().recip();
+        let t0 = (self.min - ray.position()) * inverse;
+        let t1 = (self.max - ray.position()) * inverse;
+        let near = t0.min(t1);
+        let far = t0.max(t1);
+
+        // The axes of the last face entered and the first face left
+        let near_axis = (0..3)
+            .max_by(|a, b| near[*a].total_cmp(&near[*b]))
+            .unwrap_or(0);
+        let far_axis = (0..3)
+            .min_by(|a, b| far[*a].total_cmp(&far[*b]))
+            .unwrap_or(0);
+        let t_near = near[near_axis];
+        let t_far = far[far_axis];
+
+        // Rays parallel to a face and level with it give NaN, and count as a miss
+        if t_near >= t_far || t_near.is_nan() || t_far.is_nan() {
+            return Vec::new();
+        }
+
+        vec![
+            Hit::new(
+                ray.position() + ray.direction() * t_near,
+                face_normal(near_axis, -ray.direction()[near_axis]),
+                t_near,
+                true,
+                self.obj_index,
+                self.csg_index,
+            ),
+            Hit::new(
+                ray.position() + ray.direction() * t_far,
+                face_normal(far_axis, ray.direction()[far_axis]),
+                t_far,
+                false,
+                self.obj_index,
+                self.csg_index,
+            ),
+        ]
+    }
+
+    fn surface_area(&self) -> f64 {
+        let size = self.max - self.min;
+        2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)> {
+        let mut rng = rand::thread_rng();
+        let size = self.max - self.min;
+
+        // Picks a face in proportion to its area, then a point on it
+        let areas = [size.y * size.z, size.z * size.x, size.x * size.y];
+        let mut i: f64 = rng.gen_range(0. ..areas.iter().sum::<f64>().max(f64::MIN_POSITIVE));
+        let axis = (0..3)
+            .find(|a| {
+                i -= areas[*a];
+                i <= 0.
+            })
+            .unwrap_or(2);
+        let side = if rng.gen_bool(0.5) { 1. } else { -1. };
+
+        let mut pos = self.min
+            + size
+                * DVec3::new(
+                    rng.gen_range(0. ..1.),
+                    rng.gen_range(0. ..1.),
+                    rng.gen_range(0. ..1.),
+                );
+        pos[axis] = if side > 0. {
+            self.max[axis]
+        } else {
+            self.min[axis]
+        };
+
+        Some((pos, face_normal(axis, side)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> AabbPrimitive {
+        AabbPrimitive::new(DVec3::ONE, -DVec3::ONE, 3, 5)
+    }
+
+    #[test]
+    fn hit_gives_entry_and_exit() {
+        let ray = Ray::new(DVec3::new(-5., 0.5, 0.), DVec3::X);
+        let hits = unit_box().intersection(&ray);
+
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].get_distance() - 4.).abs() < 1e-9);
+        assert!((hits[1].get_distance() - 6.).abs() < 1e-9);
+        assert!(hits[0].get_dir());
+        assert!(!hits[1].get_dir());
+        assert_eq!(*hits[0].normal(), -DVec3::X);
+        assert_eq!(*hits[1].normal(), DVec3::X);
+        assert!(hits[0].pos().distance(DVec3::new(-1., 0.5, 0.)) < 1e-9);
+        assert_eq!(hits[0].get_object_index(), 3);
+        assert_eq!(hits[0].get_csg_index(), 5);
+    }
+
+    #[test]
+    fn normals_follow_the_last_face_entered() {
+        let ray = Ray::new(DVec3::new(0.5, 5., 0.9), DVec3::new(0., -1., 0.));
+        let hits = unit_box().intersection(&ray);
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(*hits[0].normal(), DVec3::Y);
+        assert_eq!(*hits[1].normal(), -DVec3::Y);
+    }
+
+    #[test]
+    fn miss() {
+        let ray = Ray::new(DVec3::new(-5., 2., 0.), DVec3::X);
+        assert!(unit_box().intersection(&ray).is_empty());
+
+        let ray = Ray::new(DVec3::new(-5., 0., 0.), DVec3::new(1., 1., 0.));
+        assert!(unit_box().intersection(&ray).is_empty());
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let ray = Ray::new(DVec3::ZERO, DVec3::Z);
+        let hits = unit_box().intersection(&ray);
+
+        assert_eq!(hits.len(), 2);
+        assert!((hits[0].get_distance() + 1.).abs() < 1e-9);
+        assert!((hits[1].get_distance() - 1.).abs() < 1e-9);
+        assert_eq!(*hits[1].normal(), DVec3::Z);
+    }
+
+    #[test]
+    fn box_behind_the_ray() {
+        let ray = Ray::new(DVec3::new(5., 0., 0.), DVec3::X);
+        let hits = unit_box().intersection(&ray);
+
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit.get_distance() < 0.));
+    }
+
+    #[test]
+    fn ray_level_with_a_face_misses() {
+        let ray = Ray::new(DVec3::new(-5., 1., 0.), DVec3::X);
+        assert!(unit_box().intersection(&ray).is_empty());
+
+        let ray = Ray::new(DVec3::new(-5., 1.5, 0.), DVec3::X);
+        assert!(unit_box().intersection(&ray).is_empty());
+    }
+
+    #[test]
+    fn surface() {
+        let aabb = AabbPrimitive::new(DVec3::ZERO, DVec3::new(1., 2., 3.), 0, 0);
+        assert!((aabb.surface_area() - 22.).abs() < 1e-9);
+
+        for _ in 0..100 {
+            let (pos, normal) = aabb.sample_surface().unwrap();
+            let axis = (0..3).find(|a| normal[*a] != 0.).unwrap();
+            let face = if normal[axis] > 0. {
+                aabb.max
+            } else {
+                aabb.min
+            };
+            assert_eq!(pos[axis], face[axis]);
+            assert!(pos.cmpge(aabb.min).all() && pos.cmple(aabb.max).all());
+        }
+    }
+}
@@ -4,12 +4,15 @@ use crate::primitives::primitive::Primitive;
 use crate::ray::Ray;
 use bvh::aabb::{Bounded, AABB};
 use bvh::bounding_hierarchy::BHShape;
-use glam::{DVec3, Vec3};
+use glam::{DVec2, DVec3, Vec3};
 
 #[derive(Debug, Clone)]
 pub struct PlanePrimitive {
     normal: DVec3,
     d: f64,
+    // Directions along the plane for the texture coordinates
+    u_axis: DVec3,
+    v_axis: DVec3,
     node_index: usize,
     obj_index: usize,
     csg_index: usize,
@@ -17,9 +20,12 @@ pub struct PlanePrimitive {
 
 impl PlanePrimitive {
     pub fn new(point: DVec3, normal: DVec3, obj_index: usize, csg_index: usize) -> Self {
+        let (u_axis, v_axis) = normal.any_orthonormal_pair();
         Self {
             normal,
             d: normal.dot(point),
+            u_axis,
+            v_axis,
             node_index: 0,
             obj_index,
             csg_index,
@@ -65,6 +71,8 @@ impl Primitive for PlanePrimitive {
 
         let p = ray.position() + t * ray.direction();
 
+        // Texture coordinates are the distance along the plane, so textures repeat
+        //  every unit
         vec![Hit::new(
             p,
             self.normal,
@@ -72,6 +80,17 @@ impl Primitive for PlanePrimitive {
             self.normal.dot(ray.direction()) < 0.,
             self.obj_index,
             self.csg_index,
-        )]
+        )
+        .with_uv(DVec2::new(p.dot(self.u_axis), p.dot(self.v_axis)))
+        .with_tangents(self.u_axis, self.v_axis)]
+    }
+
+    // Planes are infinite so cannot be sampled
+    fn surface_area(&self) -> f64 {
+        0.
+    }
+
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)> {
+        None
     }
 }
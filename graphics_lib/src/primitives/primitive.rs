@@ -2,6 +2,7 @@ use crate::hit::Hit;
 use crate::ray::Ray;
 use bvh::aabb::Bounded;
 use bvh::bounding_hierarchy::BHShape;
+use glam::DVec3;
 use std::fmt::Debug;
 
 // BHShape and Bounded are traits required for the BVH data structure
@@ -14,4 +15,11 @@ pub trait Primitive: BHShape + Bounded + Debug {
 
     // Finds all intersections of this object and a ray
     fn intersection(&self, ray: &Ray) -> Vec<Hit>;
+
+    // Surface area of the primitive, zero for unbounded primitives
+    fn surface_area(&self) -> f64;
+
+    // Picks a random point on the surface with its outward normal
+    // Returns None for unbounded primitives which cannot be sampled
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)>;
 }
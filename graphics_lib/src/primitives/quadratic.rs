@@ -152,4 +152,13 @@ impl Primitive for QuadraticPrimitive {
             ]
         }
     }
+
+    // Quadratics may be unbounded so are never sampled
+    fn surface_area(&self) -> f64 {
+        0.
+    }
+
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)> {
+        None
+    }
 }
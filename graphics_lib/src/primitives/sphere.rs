@@ -1,10 +1,12 @@
 use crate::constants::EPSILON;
+use crate::fibonacci_spiral::fibonacci_spiral_random;
 use crate::hit::Hit;
 use crate::primitives::primitive::Primitive;
 use crate::ray::Ray;
 use bvh::aabb::{Bounded, AABB};
 use bvh::bounding_hierarchy::BHShape;
-use glam::DVec3;
+use glam::{DVec2, DVec3};
+use std::f64::consts::PI;
 
 #[derive(Clone, Debug)]
 pub struct SpherePrimitive {
@@ -26,6 +28,29 @@ impl SpherePrimitive {
         }
     }
 }
+
+// Texture coordinates of a point on a sphere from its normal
+// u goes around the equator, 0.5 at +z and wrapping from 1 back to 0 at -z
+// v goes from the top to the bottom
+fn sphere_uv(normal: DVec3) -> DVec2 {
+    DVec2::new(
+        0.5 + normal.x.atan2(normal.z) / (2. * PI),
+        normal.y.clamp(-1., 1.).acos() / PI,
+    )
+}
+
+// Directions u and v increase in at a point on a sphere from its normal
+fn sphere_tangents(normal: DVec3) -> (DVec3, DVec3) {
+    let tangent = DVec3::new(normal.z, 0., -normal.x);
+    let horizontal = (normal.x * normal.x + normal.z * normal.z).sqrt();
+    let bitangent = DVec3::new(
+        normal.y * normal.x,
+        -horizontal * horizontal,
+        normal.y * normal.z,
+    );
+    (tangent, bitangent)
+}
+
 impl BHShape for SpherePrimitive {
     fn set_bh_node_index(&mut self, n: usize) {
         self.node_index = n
@@ -75,24 +100,36 @@ impl Primitive for SpherePrimitive {
             let t_second = t0.max(t1);
             let pos2 = ray.position() + ray.direction() * t_second;
 
+            let normal1 = (pos1 - self.center).normalize();
+            let normal2 = (pos2 - self.center).normalize();
+
+            let (tangent1, bitangent1) = sphere_tangents(normal1);
+            let (tangent2, bitangent2) = sphere_tangents(normal2);
+
             vec![
-                Hit::new(
-                    pos1,
-                    (pos1 - self.center).normalize(),
-                    t_first,
-                    true,
-                    self.obj_index,
-                    self.csg_index,
-                ),
+                Hit::new(pos1, normal1, t_first, true, self.obj_index, self.csg_index)
+                    .with_uv(sphere_uv(normal1))
+                    .with_tangents(tangent1, bitangent1),
                 Hit::new(
                     pos2,
-                    (pos2 - self.center).normalize(),
+                    normal2,
                     t_second,
                     false,
                     self.obj_index,
                     self.csg_index,
-                ),
+                )
+                .with_uv(sphere_uv(normal2))
+                .with_tangents(tangent2, bitangent2),
             ]
         }
     }
+
+    fn surface_area(&self) -> f64 {
+        4. * PI * self.rad * self.rad
+    }
+
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)> {
+        let normal = fibonacci_spiral_random();
+        Some((self.center + normal * self.rad, normal))
+    }
 }
@@ -3,7 +3,8 @@ use crate::primitives::primitive::Primitive;
 use crate::ray::Ray;
 use bvh::aabb::{Bounded, AABB};
 use bvh::bounding_hierarchy::BHShape;
-use glam::{DMat3, DVec3, Vec3};
+use glam::{DVec2, DVec3};
+use rand::Rng;
 
 const EPSILON: f64 = 1E-5;
 
@@ -16,7 +17,11 @@ pub struct TrianglePrimitive {
     an: DVec3,
     bn: DVec3,
     cn: DVec3,
-    mat: DMat3,
+    // Texture coordinates of each vertex
+    uvs: [DVec2; 3],
+    // Directions u and v increase in across the triangle
+    tangent: DVec3,
+    bitangent: DVec3,
     d: f64,
     smoothing: bool,
     node_index: usize,
@@ -25,6 +30,7 @@ pub struct TrianglePrimitive {
 }
 
 impl TrianglePrimitive {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         a: DVec3,
         b: DVec3,
@@ -44,7 +50,9 @@ impl TrianglePrimitive {
             an,
             bn,
             cn,
-            mat: DMat3::from_cols(a, b, c).inverse(),
+            uvs: [DVec2::ZERO; 3],
+            tangent: b - a,
+            bitangent: n.cross(b - a),
             n,
             d: a.dot(n),
             smoothing,
@@ -53,6 +61,21 @@ impl TrianglePrimitive {
             csg_index,
         }
     }
+
+    // Adds texture coordinates for each vertex of the triangle
+    pub fn with_uvs(mut self, uvs: [DVec2; 3]) -> TrianglePrimitive {
+        self.uvs = uvs;
+
+        // Solves for the directions u and v increase in from the edges
+        let (edge1, edge2) = (self.b - self.a, self.c - self.a);
+        let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+        let determinant = duv1.x * duv2.y - duv2.x * duv1.y;
+        if determinant.abs() > EPSILON {
+            self.tangent = (edge1 * duv2.y - edge2 * duv1.y) / determinant;
+            self.bitangent = (edge2 * duv1.x - edge1 * duv2.x) / determinant;
+        }
+        self
+    }
 }
 
 impl BHShape for TrianglePrimitive {
@@ -109,31 +132,54 @@ impl Primitive for TrianglePrimitive {
         let t2 = v2 >= -EPSILON;
 
         if t0 && t1 && t2 {
-            vec![if self.smoothing {
-                let res = self.mat.mul_vec3(p);
-
-                let smoothed_normal = self.an * res.x + self.bn * res.y + self.cn * res.z;
-
-                Hit::new(
-                    p,
-                    smoothed_normal,
-                    t,
-                    normal.dot(ray.direction()) < 0.,
-                    self.obj_index,
-                    self.csg_index,
-                )
+            // Each value is twice the area of the triangle opposite a vertex, so
+            //  dividing by the total gives the barycentric coordinates
+            let total = v0 + v1 + v2;
+            let barycentric = if total.abs() < EPSILON {
+                DVec3::new(1., 0., 0.)
+            } else {
+                DVec3::new(v1, v2, v0) / total
+            };
+
+            let hit_normal = if self.smoothing {
+                self.an * barycentric.x + self.bn * barycentric.y + self.cn * barycentric.z
             } else {
-                Hit::new(
-                    p,
-                    normal,
-                    t,
-                    normal.dot(ray.direction()) < 0.,
-                    self.obj_index,
-                    self.csg_index,
-                )
-            }]
+                normal
+            };
+
+            let uv = self.uvs[0] * barycentric.x
+                + self.uvs[1] * barycentric.y
+                + self.uvs[2] * barycentric.z;
+
+            vec![Hit::new(
+                p,
+                hit_normal,
+                t,
+                normal.dot(ray.direction()) < 0.,
+                self.obj_index,
+                self.csg_index,
+            )
+            .with_uv(uv)
+            .with_barycentric(barycentric)
+            .with_tangents(self.tangent, self.bitangent)]
         } else {
             vec![]
         }
     }
+
+    fn surface_area(&self) -> f64 {
+        (self.b - self.a).cross(self.c - self.a).length() / 2.
+    }
+
+    fn sample_surface(&self) -> Option<(DVec3, DVec3)> {
+        // Uniform barycentric sample, folding points outside the triangle back in
+        let mut rng = rand::thread_rng();
+        let (u, v): (f64, f64) = (rng.gen_range(0. ..1.), rng.gen_range(0. ..1.));
+        let (u, v) = if u + v > 1. { (1. - u, 1. - v) } else { (u, v) };
+
+        Some((
+            self.a + u * (self.b - self.a) + v * (self.c - self.a),
+            self.n,
+        ))
+    }
 }
@@ -1,3 +1,4 @@
+use crate::medium::MediumStack;
 use glam::DVec3;
 
 // represents a light ray
@@ -5,6 +6,8 @@ use glam::DVec3;
 pub struct Ray {
     position: DVec3,
     direction: DVec3,
+    // The transparent objects the ray is travelling inside of
+    media: MediumStack,
 }
 
 impl Ray {
@@ -12,9 +15,16 @@ impl Ray {
         Ray {
             position,
             direction: direction.normalize(),
+            media: MediumStack::new(),
         }
     }
 
+    // Sets the transparent objects the ray starts inside of
+    pub(crate) fn with_media(mut self, media: MediumStack) -> Ray {
+        self.media = media;
+        self
+    }
+
     pub(crate) fn bvh_ray(&self) -> bvh::ray::Ray {
         // Start the BVH ray far back so it passes through all the objects, even those
         // before the start of the ray
@@ -31,4 +41,8 @@ impl Ray {
     pub(crate) fn direction(&self) -> DVec3 {
         self.direction
     }
+
+    pub(crate) fn media(&self) -> MediumStack {
+        self.media
+    }
 }
@@ -0,0 +1,90 @@
+use crate::color::Color;
+use crate::constants::{CAUSTIC_RAD, MAX_RECURSE_DEPTH, PHOTON_RAD};
+use crate::hit::Hit;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use std::f64::consts::PI;
+
+// Number of photons or caustics found around a hit that shows as halfway up the
+//  heat map, so sparse areas still stand out from empty ones
+const PHOTON_DENSITY_SCALE: f64 = 1000.;
+const CAUSTIC_DENSITY_SCALE: f64 = 10.;
+
+// What Scene::render shows, either the normal shaded image or a diagnostic view that
+//  overrides every material
+// Rays that miss every object are black in the diagnostic views
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RenderMode {
+    #[default]
+    Shaded,
+    // Texture coordinates as red and green, repeating every unit
+    Uv,
+    // Barycentric coordinates within triangles as red, green and blue
+    Barycentric,
+    // A different color for each object, or each leaf of a CSG tree
+    ObjectIndex,
+    CsgIndex,
+    // White where the surface faces the camera, black side on
+    FacingRatio,
+    // Heat maps of the number of photons and caustics near the hit, blue for few
+    //  and red for many
+    PhotonDensity,
+    CausticDensity,
+    // Heat map of the deepest recursion reached by any ray for the pixel, blue for
+    //  none and red for the maximum
+    RecursionDepth,
+}
+
+impl RenderMode {
+    // The color of the first hit of a camera ray in one of the diagnostic views
+    pub(crate) fn debug_color(&self, ray: Ray, hit: &Hit, scene: &Scene) -> Color {
+        match self {
+            RenderMode::Uv => {
+                let uv = hit.uv();
+                Color::new(uv.x.rem_euclid(1.), uv.y.rem_euclid(1.), 0.)
+            }
+            RenderMode::Barycentric => {
+                let barycentric = hit.barycentric();
+                Color::new(barycentric.x, barycentric.y, barycentric.z)
+            }
+            RenderMode::ObjectIndex => index_color(hit.get_object_index()),
+            RenderMode::CsgIndex => index_color(hit.get_csg_index()),
+            RenderMode::FacingRatio => {
+                Color::new_grey(hit.normal().dot(ray.direction()).abs().min(1.))
+            }
+            RenderMode::PhotonDensity => {
+                let count = scene.get_photons(*hit.pos(), PHOTON_RAD).len() as f64;
+                heat_color(count / (count + PHOTON_DENSITY_SCALE))
+            }
+            RenderMode::CausticDensity => {
+                let count = scene.get_caustics(*hit.pos(), CAUSTIC_RAD).len() as f64;
+                heat_color(count / (count + CAUSTIC_DENSITY_SCALE))
+            }
+            // These are found by shading the ray as usual
+            RenderMode::Shaded | RenderMode::RecursionDepth => Color::new_black(),
+        }
+    }
+
+    // The color for the deepest recursion reached
+    pub(crate) fn depth_color(depth: usize) -> Color {
+        heat_color(depth as f64 / MAX_RECURSE_DEPTH as f64)
+    }
+}
+
+// A color for an index, spreading the hues of nearby indices far apart
+fn index_color(index: usize) -> Color {
+    // Steps around the color wheel by the golden angle
+    let hue = (index as f64 * 0.618_033_988_75).fract() * 2. * PI;
+    let channel = |offset: f64| 0.55 + 0.45 * (hue + offset).cos();
+    Color::new(channel(0.), channel(-2. * PI / 3.), channel(2. * PI / 3.))
+}
+
+// A color going from blue at 0, through green at a half, to red at 1
+fn heat_color(t: f64) -> Color {
+    let t = t.clamp(0., 1.);
+    Color::new(
+        (2. * t - 1.).clamp(0., 1.),
+        1. - (2. * t - 1.).abs(),
+        (1. - 2. * t).clamp(0., 1.),
+    )
+}
@@ -1,25 +1,42 @@
+use crate::backgrounds::background::Background;
+use crate::backgrounds::color_background::ColorBackground;
 use crate::cameras::camera::Camera;
 use crate::color::Color;
 use crate::constants::{
-    EPSILON, MAX_PHOTON_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT, NUMBER_CAUSTICS_PER_LIGHT_PER_OBJ,
-    NUMBER_PHOTONS_PER_LIGHT,
+    EPSILON, FOG_DISTANCE, MAX_DEPTH, MAX_PHOTON_RECURSE_DEPTH, MIN_RECURSE_COEFFICIENT,
+    NUMBER_CAUSTICS_PER_LIGHT_PER_OBJ, NUMBER_PHOTONS_PER_LIGHT, SHADOW_EPSILON,
 };
 use crate::frame_buffer::{FrameBuffer, Pixel};
 use crate::hit::Hit;
 use crate::lights::light::Light;
 use crate::materials::material::Material;
+use crate::medium::Medium;
 use crate::objects::object::Object;
 use crate::photon::Photon;
 use crate::primitives::primitive::Primitive;
 use crate::ray::Ray;
+use crate::render_mode::RenderMode;
+use crate::volumes::volume::{
+    henyey_greenstein, optical_depth, sample_henyey_greenstein, sample_interaction, step_count,
+    Volume,
+};
 use bvh::aabb::{Bounded, AABB};
 use bvh::bounding_hierarchy::BHShape;
 use bvh::bvh::BVH;
 use glam::DVec3;
 use kd_tree::KdTree;
+use rand::Rng;
 use rayon::prelude::*;
+use std::cell::Cell;
+use std::f64::consts::PI;
 use std::fmt::Debug;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+
+thread_local! {
+    // Deepest recursion reached by the ray being rendered on this thread, for
+    //  RenderMode::RecursionDepth
+    static MAX_REFLECTION_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
 
 // Scene object
 #[derive(Debug)]
@@ -29,6 +46,12 @@ pub struct Scene {
     materials: Vec<Box<dyn Material + Sync + Send>>,
     objects: Vec<Box<dyn Object + Sync + Send>>,
     camera: Box<dyn Camera + Sync + Send>,
+    // What rays that miss every object see
+    background: Arc<dyn Background + Sync + Send>,
+    // Volume filling the space outside of objects, if any
+    fog: Option<Arc<dyn Volume + Sync + Send>>,
+    // What render shows, the shaded image or a diagnostic view
+    render_mode: RenderMode,
     // The Bounding View Hierarchy data structure is an external crate
     // https://crates.io/crates/bvh
     bvh: BVH,
@@ -68,6 +91,9 @@ impl Scene {
             materials,
             primitives,
             objects,
+            background: Arc::new(ColorBackground::new(Color::new_black())),
+            fog: None,
+            render_mode: RenderMode::Shaded,
             bvh,
             // These will be instantly populated
             // Only not populated as it it useful to have the scene initialised
@@ -96,6 +122,53 @@ impl Scene {
         &self.lights
     }
 
+    // Sets the background, this is black by default
+    // To also light the scene from the background add an EnvironmentLight sharing it
+    pub fn set_background(&mut self, background: Arc<dyn Background + Sync + Send>) {
+        self.background = background;
+    }
+
+    pub fn get_background(&self) -> &Arc<dyn Background + Sync + Send> {
+        &self.background
+    }
+
+    // Fills the space outside of objects with a volume, there is no fog by default
+    // The photon maps are built with the scene, so rebuilds them to scatter in the fog
+    pub fn set_fog(&mut self, fog: Arc<dyn Volume + Sync + Send>) {
+        self.fog = Some(fog);
+        self.photon_map = self.photon_map();
+        println!("-- Rebuilt photon map --");
+        self.caustic_map = self.caustic_map();
+        println!("-- Rebuilt caustic map --");
+    }
+
+    // Sets what render shows, the shaded image by default
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    // Calculates the color for a ray from the camera, as shown by the render mode
+    fn calc_camera_ray(&self, ray: Ray) -> (Color, f64) {
+        match self.render_mode {
+            RenderMode::Shaded => self.calc_ray(ray, Color::new_grey(1.), 0),
+            RenderMode::RecursionDepth => {
+                MAX_REFLECTION_DEPTH.with(|d| d.set(0));
+                let (_, depth) = self.calc_ray(ray, Color::new_grey(1.), 0);
+                (
+                    RenderMode::depth_color(MAX_REFLECTION_DEPTH.with(|d| d.get())),
+                    depth,
+                )
+            }
+            mode => match self.next_hit(ray, 0.) {
+                Some(hit) => (
+                    mode.debug_color(ray, &hit, self),
+                    hit.get_distance().min(MAX_DEPTH),
+                ),
+                None => (Color::new_black(), 0.),
+            },
+        }
+    }
+
     // Calculates the color for a ray in the scene
     pub fn calc_ray(
         &self,
@@ -103,34 +176,127 @@ impl Scene {
         reflection_power: Color,
         reflection_depth: usize,
     ) -> (Color, f64) {
-        let intersections = self
-            .intersection(ray)
-            .filter(|s| s.get_dir() && s.get_distance() > 0.)
-            .collect::<Vec<Hit>>();
+        MAX_REFLECTION_DEPTH.with(|d| d.set(d.get().max(reflection_depth)));
 
-        // Only consider the case where it hits something, otherwise return black
-        if let Some(v) = intersections.first() {
+        let hit = self.next_hit(ray, 0.);
+
+        // Light scattered towards the ray by fog or a volume it travels through, and the
+        //  fraction of the light from further along that gets through
+        let (scattered, transmittance) = match self.ray_volume(&ray) {
+            Some(volume) => {
+                let distance = hit.as_ref().map_or(FOG_DISTANCE, |h| h.get_distance());
+                self.volume_scattering(ray, volume, distance.min(FOG_DISTANCE))
+            }
+            None => (Color::new_black(), 1.),
+        };
+
+        // Only consider the case where it hits something, otherwise return the background
+        if let Some(v) = hit {
             (
-                self.materials[self.objects[v.get_object_index()].get_material(v)].compute(
-                    ray,
-                    v,
-                    Color::new(1., 1., 1.),
-                    self,
-                    reflection_depth,
-                    reflection_power,
-                ),
-                v.get_distance().min(100.),
+                self.calc_hit(ray, &v, reflection_power * transmittance, reflection_depth)
+                    * transmittance
+                    + scattered,
+                v.get_distance().min(MAX_DEPTH),
             )
         } else {
-            (Color::new(0.0, 0.0, 0.0), 0.0)
+            (
+                self.background.get_color(ray.direction()) * transmittance + scattered,
+                0.0,
+            )
+        }
+    }
+
+    // The volume a ray is travelling through, the fog if it is not inside any objects
+    fn ray_volume(&self, ray: &Ray) -> Option<&(dyn Volume + Sync + Send)> {
+        match ray.media().current() {
+            Some(medium) => self.get_medium_material(&medium).volume(),
+            None => self.fog.as_deref(),
         }
     }
 
+    // Steps along a ray through a volume up to a distance, adding the light from each
+    //  light in the scene scattered towards the start of the ray
+    // Returns the scattered light and the fraction of light from the end of the ray
+    //  that gets through
+    // Uses fixed steps, set by the step size of the volume, rather than the delta
+    //  tracking photons use, so fine detail between steps is missed
+    fn volume_scattering(&self, ray: Ray, volume: &dyn Volume, distance: f64) -> (Color, f64) {
+        let steps = step_count(volume, distance);
+        let step = distance / steps as f64;
+        let albedo = volume.albedo();
+        let anisotropy = volume.anisotropy();
+
+        // Each step is sampled at a random point within it to avoid banding
+        let mut rng = rand::thread_rng();
+        let offset: f64 = rng.gen_range(0. ..1.);
+
+        let mut depth = 0.;
+        let mut scattered = Color::new_black();
+        for i in 0..steps {
+            let pos = ray.position() + ray.direction() * ((i as f64 + offset) * step);
+            let density = volume.density(pos);
+            if density <= 0. {
+                continue;
+            }
+
+            let light = self
+                .lights
+                .iter()
+                .enumerate()
+                .flat_map(|(i, light)| light.get_samples(pos, self, i))
+                .fold(Color::new_black(), |c, (intensity, dir)| {
+                    c + intensity * henyey_greenstein(dir.dot(-ray.direction()), anisotropy)
+                });
+
+            // Scaled by PI to match the brightness of DiffuseMaterial
+            scattered = scattered
+                + light.piecewise_mul(&albedo)
+                    * (density * step * (-(depth + density * step * 0.5)).exp() * PI);
+            depth += density * step;
+        }
+
+        (scattered, (-depth).exp())
+    }
+
+    // Calculates the color of a ray at a hit already found for it
+    pub(crate) fn calc_hit(
+        &self,
+        ray: Ray,
+        hit: &Hit,
+        reflection_power: Color,
+        reflection_depth: usize,
+    ) -> Color {
+        self.get_hit_material(hit).compute(
+            ray,
+            hit,
+            Color::new(1., 1., 1.),
+            self,
+            reflection_depth,
+            reflection_power,
+        )
+    }
+
+    // Finds the first surface a ray reaches further than a distance along it
+    // This is either entering an object, the back of a two sided surface, or leaving
+    //  one of the transparent objects the ray is inside of
+    pub(crate) fn next_hit(&self, ray: Ray, min_distance: f64) -> Option<Hit> {
+        let media = ray.media();
+        self.intersection(ray).find(|h| {
+            h.get_distance() > min_distance
+                && (self.faces_ray(h) || media.contains(h.get_object_index()))
+        })
+    }
+
+    // Whether a hit is on a side of a surface that can be seen
+    fn faces_ray(&self, hit: &Hit) -> bool {
+        hit.get_dir() || self.get_hit_material(hit).is_two_sided()
+    }
+
     // Renders an image in the scene
     pub fn render(&self, width: usize, height: usize) -> FrameBuffer {
         let mut fb = FrameBuffer::new(width, height);
 
-        let mut done_count: Mutex<usize> = Mutex::new(0);
+        let done_count: Mutex<usize> = Mutex::new(0);
 
         // Builds a Vector of pixels in parallel
         let pixels: Vec<(usize, usize, Pixel)> = (0..height)
@@ -150,7 +316,7 @@ impl Scene {
                         let (col_acc, depth_acc) = rays.iter().fold(
                             (Color::new_black(), 0.),
                             |(col_acc, depth_acc), ray| {
-                                let (col, depth) = self.calc_ray(*ray, Color::new_grey(1.), 0);
+                                let (col, depth) = self.calc_camera_ray(*ray);
 
                                 (
                                     if col.is_num() { col_acc + col } else { col_acc },
@@ -220,6 +386,75 @@ impl Scene {
         hits.into_iter()
     }
 
+    // Finds how much light gets along the straight line between two points
+    // Each surface entered on the way scales the light by its material's transmittance,
+    //  so opaque objects give black and transparent objects give colored shadows
+    // Fog and volumes along the line dim the light by how dense they are
+    pub(crate) fn transmittance(&self, from: DVec3, to: DVec3) -> Color {
+        let distance = from.distance(to);
+        let ray = Ray::new(from, to - from);
+        let mut transmittance = Color::new_grey(1.);
+        let mut depth = self
+            .fog
+            .as_ref()
+            .map_or(0., |fog| optical_depth(fog.as_ref(), &ray, 0., distance));
+
+        // Volumes the line is inside of, with the distance it entered them at
+        let mut volumes: Vec<(usize, &dyn Volume, f64)> = vec![];
+
+        for h in self.intersection(ray).filter(|h| {
+            h.get_distance() > SHADOW_EPSILON && h.get_distance() < distance - SHADOW_EPSILON
+        }) {
+            let material = self.get_hit_material(&h);
+            if let Some(volume) = material.volume() {
+                if h.get_dir() {
+                    volumes.push((h.get_object_index(), volume, h.get_distance()));
+                } else {
+                    // Lines starting inside a volume leave it without entering it
+                    let start = volumes
+                        .iter()
+                        .position(|(i, _, _)| *i == h.get_object_index())
+                        .map_or(0., |i| volumes.remove(i).2);
+                    depth += optical_depth(volume, &ray, start, h.get_distance());
+                }
+                continue;
+            }
+
+            if self.faces_ray(&h) {
+                transmittance = transmittance.piecewise_mul(&material.shadow_transmittance(&h));
+
+                if transmittance.max_val() <= 0. {
+                    return transmittance;
+                }
+            }
+        }
+
+        // Volumes the end of the line is inside of
+        for (_, volume, start) in volumes {
+            depth += optical_depth(volume, &ray, start, distance);
+        }
+
+        transmittance * (-depth).exp()
+    }
+
+    // Gets the material of the object part that was hit
+    fn get_hit_material(&self, hit: &Hit) -> &(dyn Material + Sync + Send) {
+        self.materials[self.objects[hit.get_object_index()].get_material(hit)].as_ref()
+    }
+
+    // Gets the material of the object part a ray is inside of
+    // Objects only use the CSG index of a hit to pick their material
+    fn get_medium_material(&self, medium: &Medium) -> &(dyn Material + Sync + Send) {
+        self.get_hit_material(&Hit::new(
+            DVec3::ZERO,
+            DVec3::Y,
+            0.,
+            true,
+            medium.object_index(),
+            medium.csg_index(),
+        ))
+    }
+
     // Calculates the photon map for the scene
     fn photon_map(&self) -> KdTree<Photon> {
         let photons: Vec<Photon> = self
@@ -230,8 +465,8 @@ impl Scene {
                 (0..NUMBER_PHOTONS_PER_LIGHT) // Repeat this many times
                     .into_par_iter() // In parallel
                     .flat_map::<_, Vec<Photon>>(move |_| {
-                        let ray = light.generate_photon_dir(); // Generate random ray from light
-                        self.calculate_photon_ray(ray, i, 0, light.get_color())
+                        let (ray, color) = light.generate_photon(); // Generate random ray from light
+                        self.calculate_photon_ray(ray, i, 0, color)
                         // Get the photons from that ray
                     })
                     .collect::<Vec<Photon>>()
@@ -253,10 +488,29 @@ impl Scene {
         // list of intersections sorted and filtered
         let mut hits = self
             .intersection(ray)
-            .into_iter()
             .filter(|h| h.get_distance() > EPSILON);
 
-        let Some(direct_hit) = hits.find(|h| h.get_dir()) else {
+        let media = ray.media();
+        let direct_hit = hits.find(|h| self.faces_ray(h) || media.contains(h.get_object_index()));
+
+        // Photons travelling through fog or a volume may scatter before reaching a surface
+        if let Some(volume) = self.ray_volume(&ray) {
+            let distance = direct_hit
+                .as_ref()
+                .map_or(FOG_DISTANCE, |h| h.get_distance());
+            if let Some(pos) = sample_interaction(volume, &ray, distance) {
+                return self.scatter_photon(
+                    ray,
+                    pos,
+                    volume,
+                    light_index,
+                    recurse_depth,
+                    recurse_power,
+                );
+            }
+        }
+
+        let Some(direct_hit) = direct_hit else {
             return vec![];
         };
 
@@ -290,10 +544,31 @@ impl Scene {
         }
 
         // Add in shadow photons for all subsequent hits
+        // Points behind transparent objects are still partly lit, so shadow photons
+        //  only start once the light has been completely blocked
         if recurse_depth == 0 {
+            let mut transmittance = self
+                .get_hit_material(&direct_hit)
+                .shadow_transmittance(&direct_hit);
+
             res.append(
                 &mut hits
-                    .map(|h| Photon::new_shadow(*h.pos(), light_index, h.get_object_index()))
+                    .filter_map(|h| {
+                        if transmittance.max_val() <= 0. {
+                            Some(Photon::new_shadow(
+                                *h.pos(),
+                                light_index,
+                                h.get_object_index(),
+                            ))
+                        } else {
+                            if self.faces_ray(&h) {
+                                transmittance = transmittance.piecewise_mul(
+                                    &self.get_hit_material(&h).shadow_transmittance(&h),
+                                );
+                            }
+                            None
+                        }
+                    })
                     .collect::<Vec<Photon>>(),
             );
         }
@@ -301,6 +576,33 @@ impl Scene {
         res
     }
 
+    // Continues a photon scattered by a particle in a volume in a new direction
+    // The photon is either absorbed or keeps its power, changing color by the albedo
+    fn scatter_photon(
+        &self,
+        ray: Ray,
+        pos: DVec3,
+        volume: &dyn Volume,
+        light_index: usize,
+        recurse_depth: usize,
+        recurse_power: Color,
+    ) -> Vec<Photon> {
+        let albedo = volume.albedo();
+        let mut rng = rand::thread_rng();
+        if recurse_depth >= MAX_PHOTON_RECURSE_DEPTH || rng.gen_range(0. ..1.) >= albedo.magnitude()
+        {
+            return vec![];
+        }
+
+        let dir = sample_henyey_greenstein(ray.direction(), volume.anisotropy());
+        self.calculate_photon_ray(
+            Ray::new(pos, dir).with_media(ray.media()),
+            light_index,
+            recurse_depth + 1,
+            recurse_power.mul_const_mag(&albedo),
+        )
+    }
+
     // Calculates a single caustic photon for a ray of light in the scene pointed at a specific object
     // This can be called recursively by materials
     pub fn calculate_caustic(
@@ -311,17 +613,25 @@ impl Scene {
         color: Color,
         recurse_depth: usize,
     ) -> Option<Photon> {
-        let intersections: Vec<Hit> = self
-            .intersection(*ray)
-            .filter(|h| h.get_distance() > EPSILON && h.get_dir())
-            .collect();
-
-        let Some(hit) = intersections.first() else {
+        let Some(hit) = self.next_hit(*ray, EPSILON) else {
             return None;
             // Ray doesn't hit anything
         };
 
-        if hit.get_object_index() != object_index {
+        // Caustics passing through fog or a volume may be scattered out of the beam
+        if let Some(volume) = self.ray_volume(ray) {
+            if sample_interaction(volume, ray, hit.get_distance()).is_some() {
+                return None;
+            }
+        }
+
+        // Inside a transparent object the caustic carries on through any other
+        //  transparent surfaces it meets, such as a liquid inside a glass
+        let material = self.get_hit_material(&hit);
+        let passes_through = hit.get_object_index() == object_index
+            || (!ray.media().is_empty() && material.is_transparent());
+
+        if !passes_through {
             // Ray does not hit the caustic object first
             if recurse_depth == 0 {
                 // If this is the first recurse do nothing
@@ -337,14 +647,7 @@ impl Scene {
             }
         } else {
             // Get the material of the object and find compute the caustics for that material
-            self.materials[self.objects[object_index].get_material(hit)].compute_caustic_ray(
-                *ray,
-                hit,
-                self,
-                recurse_depth + 1,
-                light_index,
-                color,
-            )
+            material.compute_caustic_ray(*ray, &hit, self, recurse_depth + 1, light_index, color)
         }
     }
 
@@ -356,24 +659,23 @@ impl Scene {
             .enumerate()
             // For each object
             .flat_map(|(obj_index, obj)| {
+                // find the bounds to generate the caustic within
+                // Caustics cannot be aimed at unbounded objects, such as mirrored floors,
+                //  so they are skipped, these need a CSG intersection with a bounded
+                //  object to make caustics
+                let caustic_box = obj.get_caustic_bounds();
+                let bounded = caustic_box.0.is_finite() && caustic_box.1.is_finite();
+
                 // If the object has a material that needs caustics
-                if obj.needs_caustic(self) {
-                    // find the bounds to generate the caustic within
-                    let caustic_box = obj.get_caustic_bounds();
+                if bounded && obj.needs_caustic(self) {
                     self.lights
                         .iter()
                         .enumerate()
                         .flat_map(|(light_index, light)| {
                             (0..NUMBER_CAUSTICS_PER_LIGHT_PER_OBJ)
                                 .filter_map(|_| {
-                                    let ray = light.generate_caustic_dir(caustic_box);
-                                    self.calculate_caustic(
-                                        &ray,
-                                        obj_index,
-                                        light_index,
-                                        light.get_color(),
-                                        0,
-                                    )
+                                    let (ray, color) = light.generate_caustic(caustic_box);
+                                    self.calculate_caustic(&ray, obj_index, light_index, color, 0)
                                 })
                                 .collect::<Vec<Photon>>()
                         })
@@ -0,0 +1,6 @@
+pub mod checker_texture;
+pub mod gradient_texture;
+pub mod image_texture;
+pub mod noise_texture;
+pub mod perlin_noise;
+pub mod texture;
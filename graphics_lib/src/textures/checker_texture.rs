@@ -0,0 +1,33 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::textures::texture::Texture;
+
+// A checkerboard of two colors over the texture coordinates
+#[derive(Debug)]
+pub struct CheckerTexture {
+    first: Color,
+    second: Color,
+    // Number of squares per unit of texture coordinate
+    scale: f64,
+}
+
+impl CheckerTexture {
+    pub fn new(first: Color, second: Color, scale: f64) -> Self {
+        CheckerTexture {
+            first,
+            second,
+            scale,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn get_color(&self, hit: &Hit) -> Color {
+        let square = (hit.uv() * self.scale).floor();
+        if (square.x + square.y).rem_euclid(2.) < 1. {
+            self.first
+        } else {
+            self.second
+        }
+    }
+}
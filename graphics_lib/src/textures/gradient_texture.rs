@@ -0,0 +1,31 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::textures::texture::Texture;
+use glam::DVec2;
+
+// Blends between two colors along a direction in texture coordinates
+// The start color is at the texture origin and the end color is one unit along
+//  the direction
+#[derive(Debug)]
+pub struct GradientTexture {
+    start: Color,
+    end: Color,
+    direction: DVec2,
+}
+
+impl GradientTexture {
+    pub fn new(start: Color, end: Color, direction: DVec2) -> Self {
+        GradientTexture {
+            start,
+            end,
+            direction,
+        }
+    }
+}
+
+impl Texture for GradientTexture {
+    fn get_color(&self, hit: &Hit) -> Color {
+        let t = hit.uv().dot(self.direction).clamp(0., 1.);
+        self.start * (1. - t) + self.end * t
+    }
+}
@@ -0,0 +1,39 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::image::Image;
+use crate::textures::texture::Texture;
+use std::fs::File;
+use std::io::BufReader;
+
+// A texture from an image, repeating every unit of texture coordinate
+#[derive(Debug)]
+pub struct ImageTexture {
+    image: Image,
+    // Number of times the image repeats per unit of texture coordinate
+    scale: f64,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image, scale: f64) -> Self {
+        ImageTexture { image, scale }
+    }
+
+    // Loads the texture from a PPM file
+    // Note this can fail!
+    pub fn from_ppm_file(file: BufReader<File>, scale: f64) -> Result<Self, String> {
+        Ok(ImageTexture::new(Image::from_ppm_file(file)?, scale))
+    }
+
+    // Loads the texture from a PPM file without squaring the values, for normal maps
+    // Note this can fail!
+    pub fn from_linear_ppm_file(file: BufReader<File>, scale: f64) -> Result<Self, String> {
+        Ok(ImageTexture::new(Image::from_linear_ppm_file(file)?, scale))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn get_color(&self, hit: &Hit) -> Color {
+        let uv = hit.uv() * self.scale;
+        self.image.sample(uv.x, uv.y.rem_euclid(1.))
+    }
+}
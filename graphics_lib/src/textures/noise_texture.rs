@@ -0,0 +1,127 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::textures::perlin_noise::PerlinNoise;
+use crate::textures::texture::Texture;
+use glam::{DAffine3, DVec3};
+
+// Default number of octaves of noise summed for fractal patterns
+const DEFAULT_OCTAVES: usize = 6;
+
+// The pattern the noise is turned into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoisePattern {
+    // Plain gradient noise
+    Perlin,
+    // Fractal Brownian motion, soft cloudy detail
+    Fbm,
+    // Sum of absolute octaves, billowy with sharp creases
+    Turbulence,
+    // Bands along the x axis distorted by turbulence
+    Marble,
+    // Rings around the y axis distorted by fBm
+    Wood,
+    // High frequency speckles
+    Granite,
+}
+
+// A solid texture from 3D noise evaluated at the position of the hit, so meshes
+//  do not need texture coordinates
+// The pattern blends between the two colors
+// Positions are in world space unless given the transform applied to the object,
+//  in which case the texture moves with the object
+#[derive(Debug)]
+pub struct NoiseTexture {
+    noise: PerlinNoise,
+    pattern: NoisePattern,
+    first: Color,
+    second: Color,
+    // Number of features per unit of distance
+    scale: f64,
+    octaves: usize,
+    // Moves world space positions back into the objects space
+    inverse_transform: DAffine3,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: NoisePattern, first: Color, second: Color, scale: f64, seed: u64) -> Self {
+        NoiseTexture {
+            noise: PerlinNoise::new(seed),
+            pattern,
+            first,
+            second,
+            scale,
+            octaves: DEFAULT_OCTAVES,
+            inverse_transform: DAffine3::IDENTITY,
+        }
+    }
+
+    // Helper constructor for white marble with grey veins
+    pub fn new_marble(scale: f64, seed: u64) -> Self {
+        NoiseTexture::new(
+            NoisePattern::Marble,
+            Color::new(0.35, 0.35, 0.4),
+            Color::new(0.9, 0.9, 0.88),
+            scale,
+            seed,
+        )
+    }
+
+    // Helper constructor for light wood with dark rings
+    pub fn new_wood(scale: f64, seed: u64) -> Self {
+        NoiseTexture::new(
+            NoisePattern::Wood,
+            Color::new(0.6, 0.4, 0.2),
+            Color::new(0.3, 0.15, 0.05),
+            scale,
+            seed,
+        )
+    }
+
+    // Helper constructor for speckled grey stone
+    pub fn new_granite(scale: f64, seed: u64) -> Self {
+        NoiseTexture::new(
+            NoisePattern::Granite,
+            Color::new(0.65, 0.6, 0.58),
+            Color::new(0.15, 0.15, 0.15),
+            scale,
+            seed,
+        )
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    // Takes the transform applied to the object so the texture is in object space
+    pub fn with_transform(mut self, transform: DAffine3) -> Self {
+        self.inverse_transform = transform.inverse();
+        self
+    }
+
+    // Value of the pattern at a point in texture space, between 0 and 1
+    fn pattern_value(&self, p: DVec3) -> f64 {
+        let value = match self.pattern {
+            NoisePattern::Perlin => 0.5 + 0.5 * self.noise.noise(p),
+            NoisePattern::Fbm => 0.5 + 0.5 * self.noise.fbm(p, self.octaves),
+            NoisePattern::Turbulence => self.noise.turbulence(p, self.octaves),
+            NoisePattern::Marble => {
+                0.5 + 0.5 * (p.x + 10. * self.noise.turbulence(p, self.octaves)).sin()
+            }
+            NoisePattern::Wood => {
+                let radius = (p.x * p.x + p.z * p.z).sqrt();
+                (radius + 0.4 * self.noise.fbm(p, self.octaves)).rem_euclid(1.)
+            }
+            NoisePattern::Granite => 4. * self.noise.turbulence(p * 4., self.octaves),
+        };
+        value.clamp(0., 1.)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn get_color(&self, hit: &Hit) -> Color {
+        let p = self.inverse_transform.transform_point3(*hit.pos()) * self.scale;
+        let t = self.pattern_value(p);
+        self.first * (1. - t) + self.second * t
+    }
+}
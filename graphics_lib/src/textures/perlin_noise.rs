@@ -0,0 +1,173 @@
+use glam::DVec3;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+// Gradient directions used at each lattice point, the edges of a cube
+const GRADIENTS: [DVec3; 12] = [
+    DVec3::new(1., 1., 0.),
+    DVec3::new(-1., 1., 0.),
+    DVec3::new(1., -1., 0.),
+    DVec3::new(-1., -1., 0.),
+    DVec3::new(1., 0., 1.),
+    DVec3::new(-1., 0., 1.),
+    DVec3::new(1., 0., -1.),
+    DVec3::new(-1., 0., -1.),
+    DVec3::new(0., 1., 1.),
+    DVec3::new(0., -1., 1.),
+    DVec3::new(0., 1., -1.),
+    DVec3::new(0., -1., -1.),
+];
+
+// Seeded 3D gradient noise, based on "Improving Noise", Perlin 2002
+// The same seed always gives the same noise, so renders are repeatable
+#[derive(Debug, Clone)]
+pub struct PerlinNoise {
+    // Shuffled 0..256 repeated twice, so lookups never need wrapping
+    permutation: Vec<usize>,
+}
+
+impl PerlinNoise {
+    pub fn new(seed: u64) -> Self {
+        let mut permutation = (0..256).collect::<Vec<usize>>();
+        permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+        permutation.extend_from_within(..);
+
+        PerlinNoise { permutation }
+    }
+
+    // Noise at a point, roughly between -1 and 1 and 0 at every lattice point
+    pub fn noise(&self, p: DVec3) -> f64 {
+        let cell = p.floor();
+        let f = p - cell;
+        let [x, y, z] = [
+            (cell.x as i64).rem_euclid(256) as usize,
+            (cell.y as i64).rem_euclid(256) as usize,
+            (cell.z as i64).rem_euclid(256) as usize,
+        ];
+
+        // Dot product of the corners gradient with the offset to the point
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            let hash =
+                self.permutation[self.permutation[self.permutation[x + dx] + y + dy] + z + dz];
+            GRADIENTS[hash % 12].dot(f - DVec3::new(dx as f64, dy as f64, dz as f64))
+        };
+
+        let u = fade(f.x);
+        let v = fade(f.y);
+        let w = fade(f.z);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    // Fractal Brownian motion, sums octaves of noise each at double the frequency
+    //  and half the amplitude of the last
+    // Scaled to stay roughly between -1 and 1
+    pub fn fbm(&self, p: DVec3, octaves: usize) -> f64 {
+        let (total, max) = (0..octaves).fold((0., 0.), |(total, max), i| {
+            let amplitude = 0.5_f64.powi(i as i32);
+            (
+                total + self.noise(p * 2_f64.powi(i as i32)) * amplitude,
+                max + amplitude,
+            )
+        });
+
+        if max > 0. {
+            total / max
+        } else {
+            0.
+        }
+    }
+
+    // Like fBm but with the absolute value of each octave, giving sharp creases
+    // Between 0 and roughly 1
+    pub fn turbulence(&self, p: DVec3, octaves: usize) -> f64 {
+        let (total, max) = (0..octaves).fold((0., 0.), |(total, max), i| {
+            let amplitude = 0.5_f64.powi(i as i32);
+            (
+                total + self.noise(p * 2_f64.powi(i as i32)).abs() * amplitude,
+                max + amplitude,
+            )
+        });
+
+        if max > 0. {
+            total / max
+        } else {
+            0.
+        }
+    }
+}
+
+// Smooths the interpolation so the noise has no visible grid lines
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points spread through a few cells, including negative coordinates
+    fn points() -> impl Iterator<Item = DVec3> {
+        (0..200).map(|i| {
+            let i = i as f64;
+            DVec3::new(i * 0.173 - 17., i * 0.311 - 30., i * 0.097 + 4.)
+        })
+    }
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let (a, b, c) = (
+            PerlinNoise::new(7),
+            PerlinNoise::new(7),
+            PerlinNoise::new(8),
+        );
+        assert!(points().all(|p| a.noise(p) == b.noise(p)));
+        assert!(points().any(|p| a.noise(p) != c.noise(p)));
+    }
+
+    #[test]
+    fn zero_at_lattice_points() {
+        let noise = PerlinNoise::new(1);
+        for p in [
+            DVec3::ZERO,
+            DVec3::new(3., -2., 7.),
+            DVec3::new(-300., 12., 1.),
+        ] {
+            assert!(noise.noise(p).abs() < 1E-12);
+        }
+    }
+
+    #[test]
+    fn stays_in_range() {
+        let noise = PerlinNoise::new(3);
+        assert!(points().all(|p| noise.noise(p).abs() <= 1.5));
+        assert!(points().all(|p| noise.fbm(p, 5).abs() <= 1.5));
+        assert!(points().all(|p| (0. ..=1.5).contains(&noise.turbulence(p, 5))));
+        assert_eq!(noise.fbm(DVec3::ONE * 0.5, 0), 0.);
+    }
+
+    #[test]
+    fn repeats_every_256_cells() {
+        let noise = PerlinNoise::new(5);
+        assert!(points().all(|p| {
+            (noise.noise(p) - noise.noise(p + DVec3::new(256., -256., 512.))).abs() < 1E-9
+        }));
+    }
+}
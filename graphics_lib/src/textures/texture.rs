@@ -0,0 +1,15 @@
+use crate::color::Color;
+use crate::hit::Hit;
+use std::fmt::Debug;
+
+// Trait for textures, which give a color that varies over a surface
+pub trait Texture: Debug {
+    fn get_color(&self, hit: &Hit) -> Color;
+}
+
+// A plain color is a texture that is the same everywhere
+impl Texture for Color {
+    fn get_color(&self, _: &Hit) -> Color {
+        *self
+    }
+}
@@ -0,0 +1,3 @@
+pub mod grid_volume;
+pub mod homogeneous_volume;
+pub mod volume;
@@ -0,0 +1,181 @@
+use crate::color::Color;
+use crate::textures::perlin_noise::PerlinNoise;
+use crate::volumes::volume::Volume;
+use glam::DVec3;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+// A volume with its density stored in a 3D grid of voxels filling a box lined up
+//  with the axes, for smoke and clouds
+// The density between voxel centers is blended from the eight around it, and is 0
+//  outside the box
+// Put it in an AxisAlignedBox with the same bounds so rays find it
+// The grid is fixed in world space and does not follow transforms applied to the
+//  box, so place it with its bounds and leave the box untransformed
+#[derive(Debug, Clone)]
+pub struct GridVolume {
+    // Number of voxels along each axis
+    size: [usize; 3],
+    // Densities with x changing fastest, then y, then z
+    densities: Vec<f64>,
+    min: DVec3,
+    max: DVec3,
+    max_density: f64,
+    albedo: Color,
+    anisotropy: f64,
+}
+
+impl GridVolume {
+    pub fn new(size: [usize; 3], densities: Vec<f64>, min: DVec3, max: DVec3) -> Self {
+        assert!(size.iter().all(|s| *s > 0));
+        assert_eq!(densities.len(), size[0] * size[1] * size[2]);
+        assert!(densities.iter().all(|d| *d >= 0.));
+
+        let max_density = densities.iter().copied().fold(0., f64::max);
+        GridVolume {
+            size,
+            densities,
+            min: min.min(max),
+            max: min.max(max),
+            max_density,
+            albedo: Color::new_grey(1.),
+            anisotropy: 0.,
+        }
+    }
+
+    // Loads the densities from a raw file of one byte per voxel, x changing fastest,
+    //  then y, then z, with 255 being a density of 1
+    // Note this can fail!
+    pub fn from_raw_file(
+        mut file: BufReader<File>,
+        size: [usize; 3],
+        min: DVec3,
+        max: DVec3,
+    ) -> Result<Self, String> {
+        let mut data = vec![];
+        file.read_to_end(&mut data)
+            .map_err(|_| "Failed to read file.")?;
+
+        if data.len() != size[0] * size[1] * size[2] {
+            return Err("File size does not match the grid size.".to_string());
+        }
+
+        Ok(GridVolume::new(
+            size,
+            data.into_iter().map(|d| d as f64 / 255.).collect(),
+            min,
+            max,
+        ))
+    }
+
+    // Fills the grid with the density given by a function at the center of each voxel
+    pub fn from_fn(
+        size: [usize; 3],
+        min: DVec3,
+        max: DVec3,
+        density: impl Fn(DVec3) -> f64,
+    ) -> Self {
+        let voxel = (max - min) / DVec3::new(size[0] as f64, size[1] as f64, size[2] as f64);
+        let densities = (0..size[2])
+            .flat_map(|z| (0..size[1]).flat_map(move |y| (0..size[0]).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| {
+                density(min + voxel * (DVec3::new(x as f64, y as f64, z as f64) + 0.5)).max(0.)
+            })
+            .collect();
+
+        GridVolume::new(size, densities, min, max)
+    }
+
+    // Helper constructor for a puffy cloud filling the box, with a density up to 1
+    // Noise breaks up the edges of a ball, the same seed always gives the same cloud
+    pub fn new_cloud(min: DVec3, max: DVec3, resolution: usize, seed: u64) -> Self {
+        let noise = PerlinNoise::new(seed);
+        let center = (min + max) / 2.;
+        let half = (max - min) / 2.;
+
+        GridVolume::from_fn([resolution; 3], min, max, |p| {
+            let distance = ((p - center) / half).length();
+            let puff = noise.fbm(p * (4. / half.max_element()), 5);
+            ((1. - distance) * 2. + puff * 1.5).clamp(0., 1.)
+        })
+        .with_anisotropy(0.5)
+    }
+
+    // Multiplies every density, so the same grid can be thinner or thicker
+    pub fn with_density_scale(mut self, scale: f64) -> Self {
+        assert!(scale >= 0.);
+        self.densities.iter_mut().for_each(|d| *d *= scale);
+        self.max_density *= scale;
+        self
+    }
+
+    pub fn with_albedo(mut self, albedo: Color) -> Self {
+        self.albedo = albedo;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: f64) -> Self {
+        assert!(anisotropy > -1. && anisotropy < 1.);
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    // The corners of the box the grid fills
+    pub fn bounds(&self) -> (DVec3, DVec3) {
+        (self.min, self.max)
+    }
+
+    // Size of a voxel along each axis
+    fn voxel_size(&self) -> DVec3 {
+        (self.max - self.min)
+            / DVec3::new(
+                self.size[0] as f64,
+                self.size[1] as f64,
+                self.size[2] as f64,
+            )
+    }
+
+    // Density of a voxel, voxels past the edges repeat the edge voxels
+    fn voxel(&self, x: isize, y: isize, z: isize) -> f64 {
+        let x = x.clamp(0, self.size[0] as isize - 1) as usize;
+        let y = y.clamp(0, self.size[1] as isize - 1) as usize;
+        let z = z.clamp(0, self.size[2] as isize - 1) as usize;
+        self.densities[x + self.size[0] * (y + self.size[1] * z)]
+    }
+}
+
+impl Volume for GridVolume {
+    fn density(&self, pos: DVec3) -> f64 {
+        if pos.cmplt(self.min).any() || pos.cmpgt(self.max).any() {
+            return 0.;
+        }
+
+        // Position in voxels, measured from the center of the first voxel
+        let p = (pos - self.min) / self.voxel_size() - 0.5;
+        let base = p.floor();
+        let t = p - base;
+        let (x, y, z) = (base.x as isize, base.y as isize, base.z as isize);
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let along_x = |y, z| lerp(self.voxel(x, y, z), self.voxel(x + 1, y, z), t.x);
+        let along_y = |z| lerp(along_x(y, z), along_x(y + 1, z), t.y);
+        lerp(along_y(z), along_y(z + 1), t.z)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    fn anisotropy(&self) -> f64 {
+        self.anisotropy
+    }
+
+    // Steps of half a voxel catch the changes between them
+    fn step_size(&self) -> f64 {
+        self.voxel_size().min_element() / 2.
+    }
+}
@@ -0,0 +1,46 @@
+use crate::color::Color;
+use crate::volumes::volume::Volume;
+use glam::DVec3;
+
+// A volume with the same density everywhere, like fog or milky water
+#[derive(Debug, Clone)]
+pub struct HomogeneousVolume {
+    density: f64,
+    albedo: Color,
+    anisotropy: f64,
+}
+
+impl HomogeneousVolume {
+    pub fn new(density: f64, albedo: Color, anisotropy: f64) -> Self {
+        assert!(density >= 0.);
+        assert!(anisotropy > -1. && anisotropy < 1.);
+        HomogeneousVolume {
+            density,
+            albedo,
+            anisotropy,
+        }
+    }
+
+    // Helper constructor for a thin white fog that scatters mostly forwards
+    pub fn new_fog(density: f64) -> Self {
+        HomogeneousVolume::new(density, Color::new_grey(0.9), 0.3)
+    }
+}
+
+impl Volume for HomogeneousVolume {
+    fn density(&self, _: DVec3) -> f64 {
+        self.density
+    }
+
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+
+    fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    fn anisotropy(&self) -> f64 {
+        self.anisotropy
+    }
+}
@@ -0,0 +1,210 @@
+use crate::color::Color;
+use crate::constants::{MAX_VOLUME_STEPS, VOLUME_STEPS};
+use crate::ray::Ray;
+use glam::DVec3;
+use rand::Rng;
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+// Trait for participating media, like fog, smoke and clouds, that light scatters in
+//  as it travels through rather than only at surfaces
+pub trait Volume: Debug {
+    // Chance per unit distance of light hitting a particle at a point
+    fn density(&self, pos: DVec3) -> f64;
+
+    // The highest density anywhere in the volume, light is stepped through the volume
+    //  in distances picked for this density
+    fn max_density(&self) -> f64;
+
+    // Fraction of each color scattered by a particle, the rest is absorbed
+    fn albedo(&self) -> Color;
+
+    // Which way light tends to scatter, from -1 for straight back, through 0 for
+    //  every direction equally, to 1 for straight on
+    fn anisotropy(&self) -> f64;
+
+    // Longest step to take through the volume without missing changes in density
+    // Volumes with the same density everywhere can be crossed in any number of steps
+    fn step_size(&self) -> f64 {
+        f64::INFINITY
+    }
+}
+
+// Number of steps to take through a volume over a distance
+pub(crate) fn step_count(volume: &dyn Volume, distance: f64) -> usize {
+    let steps = (distance / volume.step_size()).ceil();
+    if steps.is_finite() {
+        (steps as usize).clamp(VOLUME_STEPS, MAX_VOLUME_STEPS)
+    } else {
+        VOLUME_STEPS
+    }
+}
+
+// Henyey-Greenstein phase function, the density of light scattered at an angle to the
+//  way it was going, given the cosine of that angle
+pub(crate) fn henyey_greenstein(cos_theta: f64, anisotropy: f64) -> f64 {
+    let g2 = anisotropy * anisotropy;
+    let denominator = 1. + g2 - 2. * anisotropy * cos_theta;
+    (1. - g2) / (4. * PI * denominator * denominator.sqrt())
+}
+
+// Picks a new direction for light going in a direction that scatters off a particle,
+//  following the Henyey-Greenstein phase function
+pub(crate) fn sample_henyey_greenstein(dir: DVec3, anisotropy: f64) -> DVec3 {
+    let mut rng = rand::thread_rng();
+    let u: f64 = rng.gen_range(0. ..1.);
+    let cos_theta = if anisotropy.abs() < 1.0E-3 {
+        1. - 2. * u
+    } else {
+        let g2 = anisotropy * anisotropy;
+        let s = (1. - g2) / (1. - anisotropy + 2. * anisotropy * u);
+        ((1. + g2 - s * s) / (2. * anisotropy)).clamp(-1., 1.)
+    };
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = rng.gen_range(0. ..2. * PI);
+
+    let (tangent, bitangent) = dir.any_orthonormal_pair();
+    (dir * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta).normalize()
+}
+
+// Total density along a ray between two distances, the fraction of light getting
+//  through is e to the minus this
+pub(crate) fn optical_depth(volume: &dyn Volume, ray: &Ray, start: f64, end: f64) -> f64 {
+    if end <= start {
+        return 0.;
+    }
+    let steps = step_count(volume, end - start);
+    let step = (end - start) / steps as f64;
+    (0..steps)
+        .map(|i| {
+            volume.density(ray.position() + ray.direction() * (start + (i as f64 + 0.5) * step))
+        })
+        .sum::<f64>()
+        * step
+}
+
+// Finds where light travelling along a ray first hits a particle before a distance,
+//  or None if it gets through
+// Uses delta tracking, stepping by distances picked for the highest density and
+//  treating the difference from the actual density as empty space
+pub(crate) fn sample_interaction(volume: &dyn Volume, ray: &Ray, distance: f64) -> Option<DVec3> {
+    let max_density = volume.max_density();
+    if max_density <= 0. {
+        return None;
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut t = 0.;
+    loop {
+        let u: f64 = rng.gen_range(0. ..1.);
+        t -= (1. - u).ln() / max_density;
+        if t >= distance {
+            return None;
+        }
+
+        let pos = ray.position() + ray.direction() * t;
+        if rng.gen_range(0. ..1.) * max_density < volume.density(pos) {
+            return Some(pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::volumes::homogeneous_volume::HomogeneousVolume;
+
+    // A volume with density 1 for x between 0 and 1, with detail every tenth of a unit
+    #[derive(Debug)]
+    struct Slab;
+
+    impl Volume for Slab {
+        fn density(&self, pos: DVec3) -> f64 {
+            if (0. ..1.).contains(&pos.x) {
+                1.
+            } else {
+                0.
+            }
+        }
+
+        fn max_density(&self) -> f64 {
+            1.
+        }
+
+        fn albedo(&self) -> Color {
+            Color::new_grey(1.)
+        }
+
+        fn anisotropy(&self) -> f64 {
+            0.
+        }
+
+        fn step_size(&self) -> f64 {
+            0.1
+        }
+    }
+
+    #[test]
+    fn step_count_is_clamped() {
+        let fog = HomogeneousVolume::new_fog(0.1);
+        assert_eq!(step_count(&fog, 1000.), VOLUME_STEPS);
+        assert_eq!(step_count(&Slab, 0.), VOLUME_STEPS);
+        assert_eq!(step_count(&Slab, 3.), 30);
+        assert_eq!(step_count(&Slab, 1000.), MAX_VOLUME_STEPS);
+    }
+
+    #[test]
+    fn optical_depth_along_ray() {
+        let fog = HomogeneousVolume::new_fog(0.2);
+        let ray = Ray::new(DVec3::new(-1., 0., 0.), DVec3::X);
+        assert!((optical_depth(&fog, &ray, 0., 5.) - 1.).abs() < 1E-9);
+        assert!((optical_depth(&Slab, &ray, 0., 3.) - 1.).abs() < 1E-9);
+        assert_eq!(optical_depth(&Slab, &ray, 2., 1.), 0.);
+    }
+
+    #[test]
+    fn henyey_greenstein_integrates_to_one() {
+        for anisotropy in [-0.5, 0., 0.3, 0.8] {
+            let steps = 10000;
+            let total = (0..steps)
+                .map(|i| {
+                    let cos_theta = -1. + 2. * (i as f64 + 0.5) / steps as f64;
+                    henyey_greenstein(cos_theta, anisotropy) * 2. * PI * 2. / steps as f64
+                })
+                .sum::<f64>();
+            assert!((total - 1.).abs() < 1E-3, "{} {}", anisotropy, total);
+        }
+    }
+
+    #[test]
+    fn sampled_directions_follow_anisotropy() {
+        let dir = DVec3::Z;
+        let mean = |anisotropy: f64| {
+            (0..20000)
+                .map(|_| sample_henyey_greenstein(dir, anisotropy).dot(dir))
+                .sum::<f64>()
+                / 20000.
+        };
+        // The mean cosine of the Henyey-Greenstein phase function is its anisotropy
+        for anisotropy in [-0.6, 0., 0.7] {
+            assert!((mean(anisotropy) - anisotropy).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn interactions_match_transmittance() {
+        let fog = HomogeneousVolume::new_fog(0.5);
+        let ray = Ray::new(DVec3::ZERO, DVec3::Y);
+        let through = (0..20000)
+            .filter(|_| sample_interaction(&fog, &ray, 2.).is_none())
+            .count() as f64
+            / 20000.;
+        assert!((through - (-1_f64).exp()).abs() < 0.02);
+
+        // Interactions only happen where there is density
+        let ray = Ray::new(DVec3::new(-1., 0., 0.), DVec3::X);
+        assert!((0..1000)
+            .filter_map(|_| sample_interaction(&Slab, &ray, 3.))
+            .all(|p| (0. ..1.).contains(&p.x)));
+    }
+}
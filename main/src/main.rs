@@ -2,15 +2,13 @@ extern crate graphics_lib;
 
 use glam::{DAffine3, DVec3};
 use graphics_lib::cameras::dof_camera::DoFCamera;
-use graphics_lib::cameras::normal_camera::NormalCamera;
 use graphics_lib::color::Color;
 use graphics_lib::lights::point_light::PointLight;
 use graphics_lib::materials::compound_material::CompoundMaterial;
 use graphics_lib::materials::false_color_material::FalseColorMaterial;
-use graphics_lib::materials::material::Material;
+use graphics_lib::materials::material_library::MaterialLibrary;
 use graphics_lib::objects::csg::CSGType::{Intersection, Subtract, Union};
 use graphics_lib::objects::csg::CSG;
-use graphics_lib::objects::cube::Cube;
 use graphics_lib::objects::object::Object;
 use graphics_lib::objects::plane::Plane;
 use graphics_lib::objects::poly_mesh::PolyMesh;
@@ -23,104 +21,42 @@ use std::io::{BufReader, Write};
 use std::{fs, vec};
 
 fn main() {
-    let materials: Vec<Box<dyn Material + Sync + Send>> = vec![
-        Box::new(CompoundMaterial::new_transparent_material_opacity(
-            // 0 -> transparent blue
-            1.05,
-            Color::new(0.9, 0.7, 0.7),
-            0.2,
-        )),
-        Box::new(CompoundMaterial::new_reflective_material(
-            // 1 -> reflective slight blue
-            Color::new(1.0, 0.3, 0.2),
-            0.8,
-        )),
-        Box::new(CompoundMaterial::new_reflective_material(
-            // 2 -> Red shiny
-            Color::new(1., 0.3, 0.8) * 0.8,
-            0.5,
-        )),
-        Box::new(
-            CompoundMaterial::new_transparent_material(1.1), // 3 -> Transparent
-        ),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 4 -> Matte blue
-            Color::new(0.4, 0.4, 0.6),
-            0.2,
-        )),
-        Box::new(CompoundMaterial::new_reflective_material(
-            // 5 -> dark slightly reflective
-            Color::new(1.2, 1., 0.4) * 0.8,
-            0.8,
-        )),
-        Box::new(CompoundMaterial::new_reflective_material(
-            // 6 -> light grey reflective
-            Color::new(0.9, 0.9, 0.9),
-            0.80,
-        )),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 7 -> blue matte
-            Color::new(0.3, 0.3, 0.8),
-            0.4,
-        )),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 8 -> blue slightly reflective
-            Color::new(0.8, 0.3, 0.3),
-            0.3,
-        )),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 9 -> grey matte
-            Color::new(0.4, 0.5, 0.6),
-            0.4,
-        )),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 10 -> green matte
-            Color::new(0.4, 0.4, 0.4),
-            0.4,
-        )),
-        Box::new(CompoundMaterial::new_matte_material(
-            // 11 -> purple matte
-            Color::new(0.3, 0.6, 0.3),
-            0.3,
-        )),
-        Box::new(FalseColorMaterial::new(0.3)), // 12 -> False color
-        Box::new(CompoundMaterial::new(
-            vec![
-                (Box::new(FalseColorMaterial::new(0.8)), 0.5),
-                (
-                    Box::new(CompoundMaterial::new_reflective_material(
-                        Color::new(0.9, 0.7, 0.7),
-                        0.8,
-                    )),
-                    0.5,
-                ),
-            ],
-            Color::new(0.9, 0.7, 0.7),
-        )), // 13 -> False color
-        Box::new(CompoundMaterial::new_transparent_material_opacity(
-            // 14 -> transparent blue
-            1.1,
-            Color::new(0.7, 0.7, 0.9),
-            0.2,
-        )),
-        Box::new(CompoundMaterial::new_transparent_material_opacity(
-            // 14 -> transparent green
-            1.15,
-            Color::new(0.7, 0.9, 0.7),
-            0.2,
-        )),
-    ];
+    let mut library =
+        MaterialLibrary::from_file(BufReader::new(File::open("../materials.txt").unwrap()))
+            .unwrap();
+
+    // Half normals shown as colors, half a pink mirror
+    let false_color_mirror = library
+        .add(
+            "false_color_mirror",
+            Box::new(CompoundMaterial::new(
+                vec![
+                    (Box::new(FalseColorMaterial::new(0.8)), 0.5),
+                    (
+                        Box::new(CompoundMaterial::new_reflective_material(
+                            Color::new(0.9, 0.7, 0.7),
+                            0.8,
+                        )),
+                        0.5,
+                    ),
+                ],
+                Color::new(0.9, 0.7, 0.7),
+            )),
+        )
+        .unwrap();
+
+    let material = |name: &str| library.index(name).unwrap();
 
     let mut file = fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open("file.ppm")
         .unwrap();
 
     let mut castle = PolyMesh::from_file(
         BufReader::new(File::open("../castle.kcply").unwrap()),
-        5,
-        // 12,
+        material("gold_reflective"),
         false,
         false,
     )
@@ -135,7 +71,10 @@ fn main() {
         ])),
     );
 
-    let mut hole = Quadratic::new([1., 0., 0., 0., 1.8, 0., 0., 1., 0., -110.], 9);
+    let mut hole = Quadratic::new(
+        [1., 0., 0., 0., 1.8, 0., 0., 1., 0., -110.],
+        material("blue_grey_matte"),
+    );
     hole.apply_transform(&DAffine3::from_translation(DVec3::new(1., 0., -6.)));
 
     let cave = CSG::new(
@@ -143,14 +82,17 @@ fn main() {
         Box::new(Plane::new(
             DVec3::new(0., -4., -2.),
             DVec3::new(0.1, 0.4, -1.),
-            4,
+            material("slate_matte"),
         )),
         Box::new(hole),
     );
 
-    let land = Sphere::new(DVec3::new(-6., -10.2, 7.), 9.0, 11);
+    let land = Sphere::new(DVec3::new(-6., -10.2, 7.), 9.0, material("green_matte"));
 
-    let mut hole_2 = Quadratic::new([1., 0., 0., 0., 1.8, 0., 0., 1., 0., -110.], 9);
+    let mut hole_2 = Quadratic::new(
+        [1., 0., 0., 0., 1.8, 0., 0., 1., 0., -110.],
+        material("blue_grey_matte"),
+    );
     hole_2.apply_transform(&DAffine3::from_translation(DVec3::new(1., 0., -6.)));
 
     let floor = CSG::new(
@@ -159,13 +101,13 @@ fn main() {
         Box::new(Plane::new(
             DVec3::new(0., -2.5, 0.),
             DVec3::new(0., 1., 0.),
-            1,
+            material("orange_reflective"),
         )),
     );
 
     let mut teapot = PolyMesh::from_file(
         BufReader::new(File::open("../teapot_smaller.kcply").unwrap()),
-        0,
+        material("pink_glass"),
         true,
         true,
     )
@@ -180,12 +122,16 @@ fn main() {
     let teapot = CSG::new(
         Intersection,
         Box::new(teapot),
-        Box::new(Sphere::new(DVec3::new(5., 1., 10.), 100., 0)),
+        Box::new(Sphere::new(
+            DVec3::new(5., 1., 10.),
+            100.,
+            material("pink_glass"),
+        )),
     );
 
     let mut teapot2 = PolyMesh::from_file(
         BufReader::new(File::open("../teapot_smaller.kcply").unwrap()),
-        14,
+        material("blue_glass"),
         true,
         true,
     )
@@ -200,12 +146,16 @@ fn main() {
     let teapot2 = CSG::new(
         Intersection,
         Box::new(teapot2),
-        Box::new(Sphere::new(DVec3::new(5.4, 1., 6.), 100., 0)),
+        Box::new(Sphere::new(
+            DVec3::new(5.4, 1., 6.),
+            100.,
+            material("blue_glass"),
+        )),
     );
 
     let mut teapot3 = PolyMesh::from_file(
         BufReader::new(File::open("../teapot_smaller.kcply").unwrap()),
-        15,
+        material("green_glass"),
         true,
         true,
     )
@@ -220,29 +170,40 @@ fn main() {
     let teapot3 = CSG::new(
         Intersection,
         Box::new(teapot3),
-        Box::new(Sphere::new(DVec3::new(5., 1., 6.), 100., 0)),
+        Box::new(Sphere::new(
+            DVec3::new(5., 1., 6.),
+            100.,
+            material("green_glass"),
+        )),
     );
 
-    // let sphere = Sphere::new(DVec3::new(4., 1., 10.), 1.5, 0);
-    // let sphere2 = Sphere::new(DVec3::new(0., 1., 10.), 1.5, 12);
+    // let sphere = Sphere::new(DVec3::new(4., 1., 10.), 1.5, material("pink_glass"));
+    // let sphere2 = Sphere::new(DVec3::new(0., 1., 10.), 1.5, material("normals"));
 
     let main_light = PointLight::new(DVec3::new(1.0, 10., -15.0), Color::new(0.9, 0.9, 0.9));
     let inner_light = PointLight::new(DVec3::new(0.05, 4.25, 6.05), Color::new(0.6, 0.55, 0.55));
 
-    // let sphere = Sphere::new(DVec3::new(-2., 6., 4.), 1.2, 1);
+    // let sphere = Sphere::new(DVec3::new(-2., 6., 4.), 1.2, material("orange_reflective"));
 
-    let mut sceptre = Quadratic::new([1., 0., 0., 0., -0.09, 0., 0., 1., 0., -0.1], 2);
+    let mut sceptre = Quadratic::new(
+        [1., 0., 0., 0., -0.09, 0., 0., 1., 0., -0.1],
+        material("magenta_shiny"),
+    );
     sceptre.apply_transform(&DAffine3::from_translation(DVec3::new(0., 0.0, -6.)));
 
-    let top = Sphere::new(DVec3::new(0., 3.5, 6.), 1.4, 2);
+    let top = Sphere::new(DVec3::new(0., 3.5, 6.), 1.4, material("magenta_shiny"));
 
     let sceptre = CSG::new(Subtract, Box::new(sceptre), Box::new(top));
 
-    let bound = Plane::new(DVec3::new(0., 3.5, 6.), DVec3::new(0., 1., 0.), 2);
+    let bound = Plane::new(
+        DVec3::new(0., 3.5, 6.),
+        DVec3::new(0., 1., 0.),
+        material("magenta_shiny"),
+    );
 
     let sceptre = CSG::new(Intersection, Box::new(sceptre), Box::new(bound));
 
-    let ball = Sphere::new(DVec3::new(0., 3.5, 6.), 0.8, 13);
+    let ball = Sphere::new(DVec3::new(0., 3.5, 6.), 0.8, false_color_mirror);
 
     let sceptre = CSG::new(Union, Box::new(sceptre), Box::new(ball));
 
@@ -263,7 +224,7 @@ fn main() {
             Box::new(main_light), //
             Box::new(inner_light),
         ],
-        materials,
+        library.into_materials(),
         // Box::new(NormalCamera::new(
         //     DVec3::new(0.5, 4., -15.),
         //     DVec3::new(-0.05, -0.2, 1.0),